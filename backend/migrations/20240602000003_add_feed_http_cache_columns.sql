@@ -0,0 +1,4 @@
+-- Remember HTTP cache validators so refreshes can use conditional GET
+ALTER TABLE feeds
+ADD COLUMN etag TEXT,
+ADD COLUMN last_modified TEXT;
@@ -0,0 +1,12 @@
+-- Per-feed refresh scheduling
+ALTER TABLE feeds
+ADD COLUMN refresh_interval_minutes INTEGER,
+ADD COLUMN next_fetch_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN ttl_minutes INTEGER,
+ADD COLUMN skip_hours INTEGER[] NOT NULL DEFAULT '{}',
+ADD COLUMN skip_days TEXT[] NOT NULL DEFAULT '{}';
+
+-- The scheduler looks up active feeds by due time
+CREATE INDEX IF NOT EXISTS idx_feeds_next_fetch_at ON feeds (next_fetch_at)
+WHERE
+    active = true;
@@ -0,0 +1,6 @@
+-- Metadata read from the feed document when subscribing
+ALTER TABLE feeds
+ADD COLUMN description TEXT,
+ADD COLUMN site_url TEXT,
+ADD COLUMN language TEXT,
+ADD COLUMN icon_url TEXT;
@@ -0,0 +1,20 @@
+-- Identify articles by their feed entry ID instead of their URL
+ALTER TABLE articles
+ADD COLUMN guid TEXT;
+
+-- Existing rows only know their URL, refreshes swap in the real entry ID
+UPDATE articles
+SET
+    guid = 'legacy:' || url;
+
+ALTER TABLE articles
+ALTER COLUMN guid
+SET NOT NULL;
+
+ALTER TABLE articles
+DROP CONSTRAINT IF EXISTS articles_url_key;
+
+ALTER TABLE articles
+ADD CONSTRAINT articles_feed_id_guid_key UNIQUE (feed_id, guid);
+
+CREATE INDEX IF NOT EXISTS idx_articles_url ON articles (url);
@@ -0,0 +1,26 @@
+-- Track changes to feed entries after they were first stored
+ALTER TABLE articles
+ADD COLUMN source_updated_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN content_hash TEXT;
+
+UPDATE articles
+SET
+    content_hash = ENCODE(
+        SHA256(CONVERT_TO(title || E'\n' || content, 'UTF8')),
+        'hex'
+    );
+
+-- Previous versions of an article, one row per change
+CREATE TABLE IF NOT EXISTS article_revisions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4 (),
+    article_id UUID NOT NULL REFERENCES articles (id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL,
+    content TEXT NOT NULL,
+    published_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    source_updated_at TIMESTAMP WITH TIME ZONE,
+    content_hash TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW ()
+);
+
+CREATE INDEX IF NOT EXISTS idx_article_revisions_article_id_created_at ON article_revisions (article_id, created_at DESC);
@@ -0,0 +1,7 @@
+-- Per-feed opt-in to replace feed excerpts with the full article text
+ALTER TABLE feeds
+ADD COLUMN fetch_full_content BOOLEAN NOT NULL DEFAULT false;
+
+-- Original feed-provided content, kept once the full text has been extracted
+ALTER TABLE articles
+ADD COLUMN excerpt TEXT;
@@ -0,0 +1,4 @@
+-- Plain-text rendering of the sanitized content, used for summaries and search.
+-- Existing rows are filled in by the backend on startup.
+ALTER TABLE articles
+ADD COLUMN content_text TEXT;
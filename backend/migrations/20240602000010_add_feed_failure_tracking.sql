@@ -0,0 +1,12 @@
+-- Track refresh failures so broken feeds back off and eventually get disabled
+ALTER TABLE feeds
+ADD COLUMN last_error TEXT,
+ADD COLUMN last_error_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0,
+ADD COLUMN last_success_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN disabled_reason TEXT;
+
+-- Feeds that have been fetched before count as healthy
+UPDATE feeds
+SET
+    last_success_at = last_fetched;
@@ -0,0 +1,3 @@
+-- Addresses a feed was permanently redirected away from
+ALTER TABLE feeds
+ADD COLUMN previous_urls TEXT[] NOT NULL DEFAULT '{}';
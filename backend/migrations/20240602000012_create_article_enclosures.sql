@@ -0,0 +1,12 @@
+-- Media attached to an article (podcast episodes, videos)
+CREATE TABLE IF NOT EXISTS article_enclosures (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4 (),
+    article_id UUID NOT NULL REFERENCES articles (id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    mime_type TEXT,
+    length BIGINT,
+    duration_seconds INTEGER,
+    thumbnail_url TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW (),
+    UNIQUE (article_id, url)
+);
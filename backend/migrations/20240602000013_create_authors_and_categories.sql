@@ -0,0 +1,35 @@
+-- Authors and categories of feed entries, shared across feeds
+CREATE TABLE IF NOT EXISTS authors (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4 (),
+    name TEXT NOT NULL UNIQUE,
+    email TEXT,
+    uri TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW ()
+);
+
+CREATE TABLE IF NOT EXISTS categories (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4 (),
+    name TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW ()
+);
+
+CREATE TABLE IF NOT EXISTS article_authors (
+    article_id UUID NOT NULL REFERENCES articles (id) ON DELETE CASCADE,
+    author_id UUID NOT NULL REFERENCES authors (id) ON DELETE CASCADE,
+    PRIMARY KEY (article_id, author_id)
+);
+
+CREATE TABLE IF NOT EXISTS article_categories (
+    article_id UUID NOT NULL REFERENCES articles (id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories (id) ON DELETE CASCADE,
+    PRIMARY KEY (article_id, category_id)
+);
+
+-- Filters match names case-insensitively and go from the name to the articles
+CREATE INDEX IF NOT EXISTS idx_authors_lower_name ON authors (LOWER(name));
+
+CREATE INDEX IF NOT EXISTS idx_categories_lower_name ON categories (LOWER(name));
+
+CREATE INDEX IF NOT EXISTS idx_article_authors_author_id ON article_authors (author_id);
+
+CREATE INDEX IF NOT EXISTS idx_article_categories_category_id ON article_categories (category_id);
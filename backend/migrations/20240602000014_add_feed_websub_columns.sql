@@ -0,0 +1,7 @@
+-- WebSub (PubSubHubbub) push subscriptions
+ALTER TABLE feeds
+ADD COLUMN websub_hub TEXT,
+ADD COLUMN websub_topic TEXT,
+ADD COLUMN websub_secret TEXT,
+ADD COLUMN websub_requested_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN websub_lease_expires_at TIMESTAMP WITH TIME ZONE;
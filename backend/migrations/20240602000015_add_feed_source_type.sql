@@ -0,0 +1,3 @@
+-- Where a feed's entries come from: an RSS/Atom/JSON Feed document or a site's JSON API
+ALTER TABLE feeds
+ADD COLUMN source_type TEXT NOT NULL DEFAULT 'rss';
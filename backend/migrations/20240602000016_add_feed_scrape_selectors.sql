@@ -0,0 +1,3 @@
+-- CSS selectors of feeds scraped from web pages without a feed
+ALTER TABLE feeds
+ADD COLUMN scrape_selectors JSONB;
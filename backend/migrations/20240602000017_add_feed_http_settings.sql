@@ -0,0 +1,3 @@
+-- Per-feed credentials and request options, encrypted by the backend
+ALTER TABLE feeds
+ADD COLUMN http_settings BYTEA;
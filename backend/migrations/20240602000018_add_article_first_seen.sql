@@ -0,0 +1,11 @@
+-- When an article was first ingested, where its published_at comes from:
+-- 'published', 'updated', 'missing' (first seen) or 'clamped_from_future' (first seen),
+-- and whether it came with the first import of its feed or a backfill of its history
+ALTER TABLE articles
+ADD COLUMN first_seen_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW (),
+ADD COLUMN date_source TEXT NOT NULL DEFAULT 'published',
+ADD COLUMN first_import BOOLEAN NOT NULL DEFAULT false;
+
+UPDATE articles
+SET
+    first_seen_at = created_at;
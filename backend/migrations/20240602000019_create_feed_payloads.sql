@@ -0,0 +1,14 @@
+-- Feed documents as they were fetched, gzipped, to re-run ingestion on them
+CREATE TABLE IF NOT EXISTS feed_payloads (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4 (),
+    feed_id UUID NOT NULL REFERENCES feeds (id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    headers JSONB NOT NULL DEFAULT '{}',
+    body BYTEA NOT NULL,
+    -- Size of the document before compression
+    size_bytes INTEGER NOT NULL,
+    fetched_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW ()
+);
+
+CREATE INDEX IF NOT EXISTS idx_feed_payloads_feed_id_fetched_at ON feed_payloads (feed_id, fetched_at DESC);
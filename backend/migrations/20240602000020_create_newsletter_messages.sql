@@ -0,0 +1,7 @@
+-- Newsletter messages already handled, so re-reading a mailbox neither stores them
+-- again nor brings back the feeds of newsletters that were deleted
+CREATE TABLE IF NOT EXISTS newsletter_messages (
+    -- The guid of the message's article: its Message-ID, or a hash without one
+    guid TEXT PRIMARY KEY,
+    received_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW ()
+);
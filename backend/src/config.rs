@@ -7,6 +7,10 @@ pub struct Settings {
     pub server: ServerSettings,
     pub database: DatabaseSettings,
     pub openai: OpenAiSettings,
+    pub scheduler: SchedulerSettings,
+    pub fetcher: FetcherSettings,
+    pub websub: WebSubSettings,
+    pub newsletters: NewsletterSettings,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +36,118 @@ pub struct OpenAiSettings {
     pub max_tokens: u32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerSettings {
+    pub enabled: bool,
+    /// Refresh interval for feeds that do not set their own
+    pub default_interval_minutes: i64,
+    /// How often the scheduler looks for feeds that are due
+    pub poll_interval_seconds: u64,
+    /// Upper bound of the random delay added to each feed's next refresh
+    pub max_jitter_seconds: u64,
+    /// Longest delay between retries of a failing feed
+    pub max_backoff_minutes: i64,
+    /// Failed refreshes in a row after which a feed is deactivated (0 never deactivates)
+    pub max_consecutive_failures: i32,
+}
+
+impl Default for SchedulerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_interval_minutes: 60,
+            poll_interval_seconds: 60,
+            max_jitter_seconds: 300,
+            max_backoff_minutes: 24 * 60,
+            max_consecutive_failures: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetcherSettings {
+    pub request_timeout_seconds: u64,
+    /// Maximum number of feeds refreshed at the same time
+    pub max_concurrent_fetches: usize,
+    /// Maximum number of simultaneous requests to a single host
+    pub max_concurrent_per_host: usize,
+    /// Largest response body read, after decompression
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Hosts, addresses and networks (CIDR) that may be fetched although they are internal
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// Base64-encoded 256-bit key encrypting per-feed HTTP settings, required to store them
+    #[serde(default)]
+    pub secret_key: String,
+    /// Most documents a backfill reads from a feed's history
+    #[serde(default = "default_max_backfill_pages")]
+    pub max_backfill_pages: usize,
+    /// Fetched documents kept per feed to reprocess them later, 0 keeps none
+    #[serde(default)]
+    pub raw_payloads_per_feed: usize,
+}
+
+impl Default for FetcherSettings {
+    fn default() -> Self {
+        Self {
+            request_timeout_seconds: 10,
+            max_concurrent_fetches: 10,
+            max_concurrent_per_host: 2,
+            max_body_bytes: default_max_body_bytes(),
+            allowed_hosts: Vec::new(),
+            secret_key: String::new(),
+            max_backfill_pages: default_max_backfill_pages(),
+            raw_payloads_per_feed: 0,
+        }
+    }
+}
+
+fn default_max_body_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_max_backfill_pages() -> usize {
+    50
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSubSettings {
+    /// Public address of this server that hubs can reach, WebSub is off when empty
+    pub callback_base_url: String,
+    /// Subscription lease requested from hubs
+    pub lease_seconds: u64,
+    /// How long before a lease expires it gets renewed
+    pub renew_before_seconds: u64,
+}
+
+impl Default for WebSubSettings {
+    fn default() -> Self {
+        Self {
+            callback_base_url: String::new(),
+            lease_seconds: 10 * 24 * 60 * 60,
+            renew_before_seconds: 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsletterSettings {
+    /// Maildir directory or mbox file newsletters are delivered to, ingestion is off when empty
+    pub mailbox_path: String,
+    /// How often the mailbox is checked for new messages
+    pub poll_interval_seconds: u64,
+}
+
+impl Default for NewsletterSettings {
+    fn default() -> Self {
+        Self {
+            mailbox_path: String::new(),
+            poll_interval_seconds: 60,
+        }
+    }
+}
+
 impl Settings {
     pub fn new() -> Result<Self, ConfigError> {
         let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
@@ -44,7 +160,13 @@ impl Settings {
             // Add local settings
             .add_source(File::with_name("config/local").required(false))
             // Add environment variables with prefix
-            .add_source(Environment::with_prefix("LAUNE").separator("__"))
+            .add_source(
+                Environment::with_prefix("LAUNE")
+                    .separator("__")
+                    .list_separator(",")
+                    .with_list_parse_key("fetcher.allowed_hosts")
+                    .try_parsing(true),
+            )
             .build()?;
 
         s.try_deserialize()
@@ -0,0 +1,337 @@
+use crate::feeds::content_hash;
+use ammonia::{Builder, UrlRelative};
+use ego_tree::NodeRef;
+use reqwest::Url;
+use scraper::{Html, Node, Selector};
+use sqlx::{PgPool, Row};
+use std::collections::HashSet;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Image hosts that only serve tracking pixels
+const TRACKER_HOSTS: &[&str] = &[
+    "feeds.feedburner.com",
+    "feedproxy.google.com",
+    "pixel.wp.com",
+    "stats.wordpress.com",
+    "www.google-analytics.com",
+    "ad.doubleclick.net",
+    "feeds.feedblitz.com",
+];
+
+/// Articles normalized per query when cleaning up stored content
+const NORMALIZE_BATCH_SIZE: i64 = 100;
+
+/// Clean feed-provided HTML so it is safe to render in the reader.
+///
+/// Scripts, iframes, styles, event handlers and tracking pixels are removed and
+/// relative links are resolved against `base_url` (the entry or feed URL).
+pub fn sanitize_html(html: &str, base_url: Option<&str>) -> String {
+    let pixels = tracking_pixels(html);
+
+    let mut builder = Builder::default();
+    builder
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .attribute_filter(move |element, attribute, value| {
+            if element == "img" && attribute == "src" && is_tracker(value, &pixels) {
+                return None;
+            }
+            Some(value.into())
+        });
+    if let Some(base) = base_url.and_then(|url| Url::parse(url).ok()) {
+        builder.url_relative(UrlRelative::RewriteWithBase(base));
+    }
+
+    builder.clean(html).to_string()
+}
+
+/// Render HTML as plain text for summarization and search.
+///
+/// Paragraphs are separated by blank lines, headings and list items keep their
+/// Markdown markers and everything else is reduced to its text.
+pub fn html_to_text(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut text = TextBuilder::default();
+    for child in fragment.tree.root().children() {
+        render_text(child, &mut text);
+    }
+    text.finish()
+}
+
+/// Sanitized HTML and plain text of a feed entry's content
+pub fn normalize(html: &str, base_url: Option<&str>) -> (String, String) {
+    let sanitized = sanitize_html(html, base_url);
+    let text = html_to_text(&sanitized);
+    (sanitized, text)
+}
+
+/// Sanitize articles stored before ingestion cleaned their content.
+///
+/// Content hashes are recomputed on the cleaned text so the next refresh does
+/// not mistake every entry for an edit.
+pub async fn normalize_stored_articles(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let mut normalized = 0;
+
+    loop {
+        let rows = sqlx::query(
+            r#"
+            SELECT a.id, a.title, a.url, a.content, a.excerpt, f.url AS feed_url
+            FROM articles a
+            JOIN feeds f ON f.id = a.feed_id
+            WHERE a.content_text IS NULL
+            LIMIT $1
+            "#,
+        )
+        .bind(NORMALIZE_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+
+        if rows.is_empty() {
+            break;
+        }
+
+        for row in rows {
+            let id: Uuid = row.get("id");
+            let title: String = row.get("title");
+            let url: String = row.get("url");
+            let feed_url: String = row.get("feed_url");
+            let base_url = if url.is_empty() { &feed_url } else { &url };
+
+            let (content, content_text) =
+                normalize(&row.get::<String, _>("content"), Some(base_url));
+            let excerpt = row
+                .get::<Option<String>, _>("excerpt")
+                .map(|excerpt| sanitize_html(&excerpt, Some(base_url)));
+            // Extracted articles are hashed on the feed's version of the text
+            let hash = content_hash(&title, excerpt.as_deref().unwrap_or(&content));
+
+            sqlx::query(
+                r#"
+                UPDATE articles
+                SET content = $1, content_text = $2, excerpt = $3, content_hash = $4
+                WHERE id = $5
+                "#,
+            )
+            .bind(&content)
+            .bind(&content_text)
+            .bind(&excerpt)
+            .bind(&hash)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+            normalized += 1;
+        }
+    }
+
+    Ok(normalized)
+}
+
+/// Run `normalize_stored_articles`, logging the outcome
+pub async fn run_normalization(pool: &PgPool) {
+    match normalize_stored_articles(pool).await {
+        Ok(0) => {}
+        Ok(count) => info!("Sanitized content of {} stored articles", count),
+        Err(e) => error!("Failed to sanitize stored articles: {}", e),
+    }
+}
+
+/// Sources of images that are declared as 1x1 (or smaller) pixels
+fn tracking_pixels(html: &str) -> HashSet<String> {
+    let fragment = Html::parse_fragment(html);
+    let images = Selector::parse("img[src]").expect("valid selector");
+    fragment
+        .select(&images)
+        .filter(|img| {
+            ["width", "height"].iter().any(|dimension| {
+                img.value()
+                    .attr(dimension)
+                    .and_then(|value| value.trim().trim_end_matches("px").parse::<u32>().ok())
+                    .is_some_and(|size| size <= 1)
+            })
+        })
+        .filter_map(|img| img.value().attr("src").map(str::to_string))
+        .collect()
+}
+
+fn is_tracker(src: &str, pixels: &HashSet<String>) -> bool {
+    pixels.contains(src)
+        || Url::parse(src)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .is_some_and(|host| TRACKER_HOSTS.contains(&host.as_str()))
+}
+
+fn render_text(node: NodeRef<Node>, text: &mut TextBuilder) {
+    match node.value() {
+        Node::Text(content) => text.push(content),
+        Node::Element(element) => {
+            let name = element.name();
+            match name {
+                "script" | "style" | "noscript" | "template" => return,
+                "br" => {
+                    text.line_break();
+                    return;
+                }
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    text.block_break();
+                    let level = name[1..].parse().unwrap_or(1);
+                    text.marker(&format!("{} ", "#".repeat(level)));
+                }
+                "li" => {
+                    text.line_break();
+                    text.marker("- ");
+                }
+                "pre" => {
+                    text.block_break();
+                    text.preformatted += 1;
+                }
+                _ if is_block(name) => text.block_break(),
+                _ => {}
+            }
+
+            for child in node.children() {
+                render_text(child, text);
+            }
+
+            match name {
+                "pre" => {
+                    text.preformatted -= 1;
+                    text.block_break();
+                }
+                "li" => text.line_break(),
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => text.block_break(),
+                _ if is_block(name) => text.block_break(),
+                _ => {}
+            }
+        }
+        _ => {
+            for child in node.children() {
+                render_text(child, text);
+            }
+        }
+    }
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div"
+            | "section"
+            | "article"
+            | "header"
+            | "footer"
+            | "blockquote"
+            | "ul"
+            | "ol"
+            | "dl"
+            | "dt"
+            | "dd"
+            | "table"
+            | "tr"
+            | "figure"
+            | "figcaption"
+            | "hr"
+    )
+}
+
+/// Accumulates text while collapsing whitespace and paragraph breaks
+#[derive(Default)]
+struct TextBuilder {
+    output: String,
+    /// Newlines owed before the next piece of text
+    pending_newlines: usize,
+    pending_space: bool,
+    /// Depth of `<pre>` elements, whose whitespace is kept
+    preformatted: usize,
+}
+
+impl TextBuilder {
+    fn push(&mut self, content: &str) {
+        if self.preformatted > 0 {
+            if !content.is_empty() {
+                self.flush();
+                self.output.push_str(content);
+            }
+            return;
+        }
+
+        for (i, word) in content.split_whitespace().enumerate() {
+            if i > 0 || content.starts_with(char::is_whitespace) {
+                self.pending_space = true;
+            }
+            self.flush();
+            self.output.push_str(word);
+        }
+        if content.ends_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+    }
+
+    /// Text that starts a line, such as a list bullet
+    fn marker(&mut self, marker: &str) {
+        self.pending_space = false;
+        self.flush();
+        self.output.push_str(marker);
+    }
+
+    fn line_break(&mut self) {
+        self.pending_newlines = self.pending_newlines.max(1);
+    }
+
+    fn block_break(&mut self) {
+        self.pending_newlines = 2;
+    }
+
+    fn flush(&mut self) {
+        if self.output.is_empty() {
+            self.pending_newlines = 0;
+            self.pending_space = false;
+        } else if self.pending_newlines > 0 {
+            let output = self.output.trim_end_matches(' ');
+            self.output.truncate(output.len());
+            self.output.push_str(&"\n".repeat(self.pending_newlines));
+        } else if self.pending_space && !self.output.ends_with([' ', '\n']) {
+            self.output.push(' ');
+        }
+        self.pending_newlines = 0;
+        self.pending_space = false;
+    }
+
+    fn finish(self) -> String {
+        self.output.trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_html_strips_active_content() {
+        let html = r#"<p onclick="steal()">Hello <a href="/post">world</a></p>
+<script>alert(1)</script><iframe src="https://evil.example/"></iframe>
+<img src="images/cat.png" alt="Cat"><img src="https://example.com/open.gif" width="1" height="1">
+<img src="https://feeds.feedburner.com/~r/blog/~4/abc">"#;
+
+        let clean = sanitize_html(html, Some("https://example.com/blog/entry"));
+        assert!(!clean.contains("onclick"));
+        assert!(!clean.contains("alert"));
+        assert!(!clean.contains("iframe"));
+        assert!(!clean.contains("open.gif"));
+        assert!(!clean.contains("feedburner"));
+        assert!(clean.contains(r#"href="https://example.com/post""#));
+        assert!(clean.contains(r#"src="https://example.com/blog/images/cat.png""#));
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<h2>Title</h2><p>First   line<br>second <b>bold</b> line.</p>\
+                    <ul><li>One</li><li>Two</li></ul><pre>let x  = 1;\nlet y = 2;</pre>";
+
+        assert_eq!(
+            html_to_text(html),
+            "## Title\n\nFirst line\nsecond bold line.\n\n- One\n- Two\n\nlet x  = 1;\nlet y = 2;"
+        );
+    }
+}
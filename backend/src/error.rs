@@ -18,6 +18,14 @@ pub enum AppError {
     #[error("Feed parsing error: {0}")]
     FeedParsingError(String),
 
+    /// A document too large to download or in no readable text encoding
+    #[error("Unreadable document: {0}")]
+    UnreadableDocument(String),
+
+    /// An outbound request to an address inside our network
+    #[error("Blocked request: {0}")]
+    BlockedRequest(String),
+
     #[error("Summarization API error: {0}")]
     SummarizationError(String),
 
@@ -37,6 +45,8 @@ impl IntoResponse for AppError {
             AppError::DatabaseError(ref e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             AppError::BadRequest(ref e) => (StatusCode::BAD_REQUEST, e.to_string()),
             AppError::FeedParsingError(ref e) => (StatusCode::BAD_REQUEST, e.to_string()),
+            AppError::UnreadableDocument(ref e) => (StatusCode::BAD_REQUEST, e.to_string()),
+            AppError::BlockedRequest(ref e) => (StatusCode::FORBIDDEN, e.to_string()),
             AppError::SummarizationError(ref e) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
             AppError::NotFound(ref e) => (StatusCode::NOT_FOUND, e.to_string()),
             AppError::ValidationError(ref e) => (StatusCode::BAD_REQUEST, e.to_string()),
@@ -1,39 +1,265 @@
 use crate::{
+    config::{FetcherSettings, SchedulerSettings, Settings, WebSubSettings},
+    content,
     error::AppError,
-    models::{Article, Feed},
+    models::{Article, DateSource, Feed, SourceType},
+    scheduler::{self, CacheHints},
 };
-use chrono::Utc;
+use aes_gcm::Aes256Gcm;
+use chrono::{DateTime, Utc};
 use feed_rs::{model::Feed as RssFeed, parser};
-use reqwest::Client;
-use sqlx::{PgPool, Row};
-use tracing::{error, info};
+use futures::future::join_all;
+use guard::AddressGuard;
+use reqwest::{
+    header::{HeaderMap, HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    redirect::Policy,
+    Client, Response, StatusCode,
+};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool, Row};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Semaphore;
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
+mod backfill;
+mod body;
+mod discovery;
+mod fulltext;
+mod guard;
+mod http_settings;
+mod ingest;
+mod payloads;
+mod sources;
+mod websub;
+
+pub use ingest::SavedEntries;
+pub use payloads::RawPayload;
+pub use sources::FeedSource;
+
+/// Result of fetching a feed document
+pub enum FetchOutcome {
+    /// A fresh document was downloaded and parsed
+    Fetched {
+        articles: Vec<FeedEntry>,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        hints: CacheHints,
+        /// New address of the feed when it was reached through permanent redirects only
+        moved_to: Option<String>,
+        /// The document as fetched, when `fetcher.raw_payloads_per_feed` keeps them
+        payload: Option<Box<RawPayload>>,
+    },
+    /// The server answered `304 Not Modified`
+    NotModified { moved_to: Option<String> },
+    /// The server answered `410 Gone`, the feed will not come back
+    Gone,
+}
+
+/// An article parsed from a feed along with its media, authors and categories
+pub struct FeedEntry {
+    pub article: Article,
+    pub enclosures: Vec<Enclosure>,
+    pub authors: Vec<Author>,
+    pub categories: Vec<String>,
+}
+
+/// Author of a feed entry, before it is stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct Author {
+    pub name: String,
+    pub email: Option<String>,
+    pub uri: Option<String>,
+}
+
+/// Media file attached to a feed entry, before it is stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enclosure {
+    pub url: String,
+    pub mime_type: Option<String>,
+    pub length: Option<i64>,
+    pub duration_seconds: Option<i32>,
+    pub thumbnail_url: Option<String>,
+}
+
+/// The minimum needed to schedule a feed refresh
+pub struct FeedRef {
+    pub id: Uuid,
+    pub title: String,
+    pub url: String,
+}
+
+/// Descriptive fields read from a feed document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_url: Option<String>,
+    pub language: Option<String>,
+    pub icon_url: Option<String>,
+    /// WebSub hub advertised with `rel="hub"`
+    pub hub_url: Option<String>,
+    /// The feed's canonical address from `rel="self"`, the topic to subscribe to
+    pub self_url: Option<String>,
+}
+
+impl FeedMetadata {
+    pub fn from_feed(feed: &RssFeed) -> Self {
+        let non_empty = |s: &str| {
+            let s = s.trim();
+            (!s.is_empty()).then(|| s.to_string())
+        };
+
+        let link = |rel: &str| {
+            feed.links
+                .iter()
+                .find(|link| link.rel.as_deref() == Some(rel))
+                .map(|link| link.href.clone())
+        };
+
+        Self {
+            title: feed.title.as_ref().and_then(|t| non_empty(&t.content)),
+            description: feed
+                .description
+                .as_ref()
+                .and_then(|d| non_empty(&d.content)),
+            // The self link points back at the feed, we want the website
+            site_url: feed
+                .links
+                .iter()
+                .find(|link| link.rel.as_deref().is_none_or(|rel| rel == "alternate"))
+                .map(|link| link.href.clone()),
+            language: feed.language.as_deref().and_then(non_empty),
+            icon_url: feed
+                .icon
+                .as_ref()
+                .or(feed.logo.as_ref())
+                .map(|image| image.uri.clone()),
+            hub_url: link("hub"),
+            self_url: link("self"),
+        }
+    }
+}
+
+/// Redirects followed when fetching a feed before giving up
+const MAX_REDIRECTS: usize = 10;
+
+/// User agent of requests for feeds without their own
+const DEFAULT_USER_AGENT: &str = "Laune RSS Reader/1.0";
+
+/// How far in the future an entry may be dated before it counts as wrong, for clock skew
+const MAX_FUTURE_DATE_MINUTES: i64 = 5;
+
 /// A struct to fetch and process RSS/Atom feeds
+#[derive(Clone)]
 pub struct FeedFetcher {
     client: Client,
+    /// Does not follow redirects, so feed fetches can tell permanent moves apart and
+    /// drop the feed's headers when a redirect leaves its origin
+    feed_client: Client,
+    /// Credentials and headers of the feed being fetched, sent with `feed_client` requests
+    /// until a redirect leaves the feed's origin
+    feed_headers: HeaderMap,
+    /// Encrypts stored feed HTTP settings, `None` without a configured key
+    cipher: Option<Aes256Gcm>,
+    guard: AddressGuard,
+    settings: FetcherSettings,
+    scheduler: SchedulerSettings,
+    websub: WebSubSettings,
 }
 
 impl FeedFetcher {
-    pub fn new() -> Self {
-        let client = Client::builder()
-            .user_agent("Laune RSS Reader/1.0")
-            .timeout(std::time::Duration::from_secs(10))
+    /// Create a fetcher with the configured settings, once per process so its
+    /// clients share their connection pools
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self::build(
+            settings.fetcher.clone(),
+            settings.scheduler.clone(),
+            settings.websub.clone(),
+        )
+    }
+
+    /// `from_settings` with each part of the settings given on its own
+    pub(crate) fn build(
+        settings: FetcherSettings,
+        scheduler: SchedulerSettings,
+        websub: WebSubSettings,
+    ) -> Self {
+        let guard = AddressGuard::new(&settings);
+        let builder = || {
+            Client::builder()
+                .user_agent(DEFAULT_USER_AGENT)
+                .timeout(std::time::Duration::from_secs(
+                    settings.request_timeout_seconds,
+                ))
+                .dns_resolver(Arc::new(guard.clone()))
+        };
+        // A default client would have neither the guard's resolver nor its
+        // redirect policy, so failing to build one is fatal
+        let client = builder()
+            .redirect(guard.redirect_policy())
             .build()
-            .unwrap_or_default();
-        Self { client }
+            .expect("valid HTTP client");
+        let feed_client = builder()
+            .redirect(Policy::none())
+            .build()
+            .expect("valid HTTP client");
+        Self {
+            client,
+            feed_client,
+            feed_headers: HeaderMap::new(),
+            cipher: http_settings::settings_cipher(&settings.secret_key),
+            guard,
+            settings,
+            scheduler,
+            websub,
+        }
+    }
+
+    /// Fetch a feed from its source and return its entries as Articles
+    pub async fn fetch_feed(&self, feed: &Feed) -> Result<FetchOutcome, AppError> {
+        let fetcher = self.for_feed(feed)?;
+        let outcome = fetcher.source(feed.source_type).fetch(feed).await;
+        outcome
+    }
+
+    /// Read the metadata of a source, failing if `url` has nothing to follow
+    pub async fn fetch_metadata(
+        &self,
+        source_type: SourceType,
+        url: &str,
+    ) -> Result<FeedMetadata, AppError> {
+        self.source(source_type).metadata(url).await
     }
 
-    /// Fetch a feed and return its entries as Articles
-    pub async fn fetch_feed(&self, feed: &Feed) -> Result<Vec<Article>, AppError> {
+    /// Fetch an RSS/Atom/JSON Feed document.
+    ///
+    /// Cache validators stored on the feed are sent as `If-None-Match` /
+    /// `If-Modified-Since`, so an unchanged feed costs a single `304` round trip.
+    /// Redirects are followed by hand to report feeds that moved permanently.
+    async fn fetch_rss(&self, feed: &Feed) -> Result<FetchOutcome, AppError> {
         info!("Fetching feed: {} ({})", feed.title, feed.url);
 
-        let response = self
-            .client
-            .get(&feed.url)
-            .send()
-            .await
-            .map_err(|e| AppError::FeedParsingError(format!("Failed to fetch feed: {}", e)))?;
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = feed.etag.as_deref().and_then(|v| v.parse().ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = feed.last_modified.as_deref().and_then(|v| v.parse().ok()) {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let (response, url, permanent) = self.get_following_redirects(&feed.url, headers).await?;
+        let moved_to = (permanent && url != feed.url).then(|| url.clone());
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            info!("Feed not modified since last fetch: {}", feed.title);
+            return Ok(FetchOutcome::NotModified { moved_to });
+        }
+
+        if response.status() == StatusCode::GONE {
+            warn!("Feed is gone: {} ({})", feed.title, url);
+            return Ok(FetchOutcome::Gone);
+        }
 
         if !response.status().is_success() {
             return Err(AppError::FeedParsingError(format!(
@@ -42,37 +268,104 @@ impl FeedFetcher {
             )));
         }
 
-        let xml = response
-            .bytes()
-            .await
-            .map_err(|e| AppError::FeedParsingError(format!("Failed to read response: {}", e)))?;
+        let etag = header_value(response.headers(), ETAG);
+        let last_modified = header_value(response.headers(), LAST_MODIFIED);
+        let max_age = scheduler::http_max_age_minutes(response.headers());
+
+        let (xml, payload) = self.read_feed_payload(response).await?;
 
-        let parsed_feed = parser::parse(xml.as_ref())
+        let parsed_feed = feed_parser(&url)
+            .parse(xml.as_slice())
             .map_err(|e| AppError::FeedParsingError(format!("Failed to parse XML: {}", e)))?;
 
+        let mut hints = CacheHints::from_document(xml.as_ref(), parsed_feed.ttl);
+        hints.merge_ttl(max_age);
+
         let articles = self.rss_to_articles(feed, parsed_feed)?;
         info!("Fetched {} articles from {}", articles.len(), feed.title);
 
-        Ok(articles)
+        Ok(FetchOutcome::Fetched {
+            articles,
+            etag,
+            last_modified,
+            hints,
+            moved_to,
+            payload,
+        })
+    }
+
+    /// GET a URL with the feed's headers, following up to `MAX_REDIRECTS` redirects.
+    ///
+    /// Returns the final response, its URL and whether every redirect on the way
+    /// was permanent (`301`/`308`).
+    async fn get_following_redirects(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+    ) -> Result<(Response, String, bool), AppError> {
+        self.source_client()
+            .get_following_redirects(url, headers)
+            .await
+    }
+
+    /// GET a page or document on behalf of the feed being fetched, with its headers
+    async fn get(&self, url: &str) -> Result<Response, AppError> {
+        let (response, _, _) = self.get_following_redirects(url, HeaderMap::new()).await?;
+        Ok(response)
+    }
+
+    /// Download a feed and read its metadata, failing if the document is not a feed
+    async fn fetch_rss_metadata(&self, url: &str) -> Result<FeedMetadata, AppError> {
+        let response = self.get(url).await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::FeedParsingError(format!(
+                "Failed to fetch feed. Status: {}",
+                response.status()
+            )));
+        }
+
+        let xml = self.read_feed(response).await?;
+
+        let parsed_feed = parser::parse(xml.as_slice()).map_err(|e| {
+            AppError::FeedParsingError(format!("{} is not a valid RSS/Atom/JSON feed: {}", url, e))
+        })?;
+
+        Ok(FeedMetadata::from_feed(&parsed_feed))
     }
 
     /// Convert RSS feed entries to our Article model
-    fn rss_to_articles(&self, feed: &Feed, rss_feed: RssFeed) -> Result<Vec<Article>, AppError> {
+    fn rss_to_articles(&self, feed: &Feed, rss_feed: RssFeed) -> Result<Vec<FeedEntry>, AppError> {
         let articles = rss_feed
             .entries
             .into_iter()
             .map(|entry| {
+                let enclosures = entry_enclosures(&entry);
+                let authors = entry_authors(&entry);
+                let categories = entry_categories(&entry);
+
+                // Podcast and video entries often describe themselves only in their media
                 let content = entry
                     .content
                     .and_then(|c| c.body)
                     .or_else(|| entry.summary.map(|s| s.content))
+                    .or_else(|| {
+                        entry
+                            .media
+                            .iter()
+                            .find_map(|media| media.description.as_ref())
+                            .map(|description| description.content.clone())
+                    })
                     .unwrap_or_default();
 
-                let published = entry.published.or(entry.updated).unwrap_or_else(Utc::now);
+                let (published, date_source) = entry_date(entry.published, entry.updated);
 
+                // Prefer the alternate link over self/replies/enclosure links
                 let url = entry
                     .links
-                    .first()
+                    .iter()
+                    .find(|l| l.rel.as_deref().is_none_or(|rel| rel == "alternate"))
+                    .or(entry.links.first())
                     .map(|l| l.href.clone())
                     .unwrap_or_default();
 
@@ -81,15 +374,38 @@ impl FeedFetcher {
                     .map(|t| t.content)
                     .unwrap_or_else(|| "Untitled".to_string());
 
-                Article {
+                // Entries with neither an ID nor a link are identified by what they say
+                let guid = if entry.id.is_empty() {
+                    format!("sha256:{}", content_hash(&title, &content))
+                } else {
+                    entry.id
+                };
+
+                let base_url = if url.is_empty() { &feed.url } else { &url };
+                let (content, content_text) = content::normalize(&content, Some(base_url));
+
+                let article = Article {
                     id: Uuid::new_v4(),
                     title,
                     url,
+                    guid,
                     feed_id: feed.id,
                     content,
+                    content_text: Some(content_text),
+                    excerpt: None,
                     published_at: published,
+                    source_updated_at: entry.updated,
+                    first_seen_at: Utc::now(),
+                    date_source,
                     created_at: Utc::now(),
                     updated_at: Utc::now(),
+                };
+
+                FeedEntry {
+                    article,
+                    enclosures,
+                    authors,
+                    categories,
                 }
             })
             .collect();
@@ -97,17 +413,39 @@ impl FeedFetcher {
         Ok(articles)
     }
 
-    /// Fetch and save all articles from a feed to the database
-    pub async fn refresh_feed(&self, pool: &PgPool, feed_id: Uuid) -> Result<usize, AppError> {
-        // Get the feed from the database
-        let feed = sqlx::query("SELECT * FROM feeds WHERE id = $1")
+    /// Load a feed from the database
+    pub async fn load_feed(&self, pool: &PgPool, feed_id: Uuid) -> Result<Feed, AppError> {
+        sqlx::query("SELECT * FROM feeds WHERE id = $1")
             .bind(feed_id)
             .map(|row: sqlx::postgres::PgRow| Feed {
                 id: row.get("id"),
                 title: row.get("title"),
                 url: row.get("url"),
+                description: row.get("description"),
+                site_url: row.get("site_url"),
+                language: row.get("language"),
+                icon_url: row.get("icon_url"),
                 active: row.get("active"),
                 last_fetched: row.get("last_fetched"),
+                etag: row.get("etag"),
+                last_modified: row.get("last_modified"),
+                refresh_interval_minutes: row.get("refresh_interval_minutes"),
+                next_fetch_at: row.get("next_fetch_at"),
+                ttl_minutes: row.get("ttl_minutes"),
+                skip_hours: row.get("skip_hours"),
+                skip_days: row.get("skip_days"),
+                fetch_full_content: row.get("fetch_full_content"),
+                last_error: row.get("last_error"),
+                last_error_at: row.get("last_error_at"),
+                consecutive_failures: row.get("consecutive_failures"),
+                last_success_at: row.get("last_success_at"),
+                disabled_reason: row.get("disabled_reason"),
+                previous_urls: row.get("previous_urls"),
+                websub_hub: row.get("websub_hub"),
+                websub_lease_expires_at: row.get("websub_lease_expires_at"),
+                source_type: row.get("source_type"),
+                scrape_selectors: row.get("scrape_selectors"),
+                http_settings: row.get("http_settings"),
                 created_at: row.get("created_at"),
                 updated_at: row.get("updated_at"),
             })
@@ -117,7 +455,17 @@ impl FeedFetcher {
                 error!("Database error fetching feed {}: {}", feed_id, e);
                 AppError::DatabaseError(e.to_string())
             })?
-            .ok_or_else(|| AppError::NotFound(format!("Feed with ID {} not found", feed_id)))?;
+            .ok_or_else(|| AppError::NotFound(format!("Feed with ID {} not found", feed_id)))
+    }
+
+    /// Fetch and save all articles from a feed to the database
+    pub async fn refresh_feed(
+        &self,
+        pool: &PgPool,
+        feed_id: Uuid,
+    ) -> Result<SavedEntries, AppError> {
+        // Get the feed from the database
+        let feed = self.load_feed(pool, feed_id).await?;
 
         // Check if feed is active
         if !feed.active {
@@ -128,74 +476,338 @@ impl FeedFetcher {
             )));
         }
 
-        // Fetch articles from the feed
-        let articles = self.fetch_feed(&feed).await?;
+        // Fetch articles from the feed, failures count towards backing off
+        let outcome = match self.fetch_feed(&feed).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                self.record_failure(pool, &feed, &e).await?;
+                return Err(e);
+            }
+        };
 
-        if articles.is_empty() {
-            info!("No articles found in feed: {}", feed.title);
+        let moved_to = match &outcome {
+            FetchOutcome::Fetched { moved_to, .. } | FetchOutcome::NotModified { moved_to } => {
+                moved_to.clone()
+            }
+            FetchOutcome::Gone => None,
+        };
+        if let Some(new_url) = moved_to {
+            self.move_feed(pool, &feed, &new_url).await?;
+        }
 
-            // Update the last_fetched timestamp
-            sqlx::query("UPDATE feeds SET last_fetched = NOW() WHERE id = $1")
+        let (articles, etag, last_modified, hints, payload) = match outcome {
+            FetchOutcome::Fetched {
+                articles,
+                etag,
+                last_modified,
+                hints,
+                payload,
+                ..
+            } => (articles, etag, last_modified, hints, payload),
+            FetchOutcome::Gone => {
+                let reason = "Deactivated because the server reported the feed as gone (410)";
+                sqlx::query(
+                    r#"
+                    UPDATE feeds
+                    SET active = false, disabled_reason = $1, last_error = $1,
+                        last_error_at = NOW()
+                    WHERE id = $2
+                    "#,
+                )
+                .bind(reason)
                 .bind(feed_id)
                 .execute(pool)
                 .await
                 .map_err(|e| AppError::DatabaseError(e.to_string()))?;
 
-            return Ok(0);
-        }
-
-        let mut saved_count = 0;
-
-        // Save each article to the database
-        for article in articles {
-            // Check if article with this URL already exists
-            let exists = sqlx::query("SELECT id FROM articles WHERE url = $1")
-                .bind(&article.url)
-                .fetch_optional(pool)
+                return Err(AppError::FeedParsingError(format!(
+                    "Feed '{}' is gone (410), it has been deactivated",
+                    feed.title
+                )));
+            }
+            FetchOutcome::NotModified { .. } => {
+                // Nothing changed upstream, keep the stored validators and hints
+                let next_fetch_at =
+                    self.next_fetch_at(feed.refresh_interval_minutes, &stored_hints(&feed));
+                sqlx::query(
+                    r#"
+                    UPDATE feeds
+                    SET last_fetched = NOW(), last_success_at = NOW(), consecutive_failures = 0,
+                        next_fetch_at = $1
+                    WHERE id = $2
+                    "#,
+                )
+                .bind(next_fetch_at)
+                .bind(feed_id)
+                .execute(pool)
                 .await
-                .map_err(|e| AppError::DatabaseError(e.to_string()))?
-                .is_some();
+                .map_err(|e| AppError::DatabaseError(e.to_string()))?;
 
-            if exists {
-                continue;
+                return Ok(SavedEntries::default());
             }
+        };
 
-            // Insert the new article
-            sqlx::query(
-                r#"
-                INSERT INTO articles
-                (id, title, url, feed_id, content, published_at)
-                VALUES ($1, $2, $3, $4, $5, $6)
-                "#,
-            )
-            .bind(article.id)
-            .bind(&article.title)
-            .bind(&article.url)
-            .bind(article.feed_id)
-            .bind(&article.content)
-            .bind(article.published_at)
-            .execute(pool)
-            .await
-            .map_err(|e| {
-                error!("Failed to save article '{}': {}", article.title, e);
-                AppError::DatabaseError(e.to_string())
-            })?;
+        if articles.is_empty() {
+            info!("No articles found in feed: {}", feed.title);
+        }
 
-            saved_count += 1;
+        // The entries and the fetch are recorded together, a failure leaves neither behind
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let saved = self.write_entries(&mut tx, &feed, articles, false).await?;
+        if let Some(payload) = &payload {
+            self.store_payload(&mut tx, feed_id, payload).await?;
         }
 
-        // Update the last_fetched timestamp
-        sqlx::query("UPDATE feeds SET last_fetched = NOW() WHERE id = $1")
-            .bind(feed_id)
-            .execute(pool)
+        // Update the last_fetched timestamp and cache validators
+        self.mark_fetched(
+            &mut tx,
+            &feed,
+            etag.as_deref(),
+            last_modified.as_deref(),
+            &hints,
+        )
+        .await?;
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        self.extract_saved(pool, &feed, &saved).await;
+        Ok(saved)
+    }
+
+    /// Record a successful fetch along with the validators and hints from the response
+    async fn mark_fetched(
+        &self,
+        conn: &mut PgConnection,
+        feed: &Feed,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+        hints: &CacheHints,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE feeds
+            SET
+                last_fetched = NOW(),
+                last_success_at = NOW(),
+                consecutive_failures = 0,
+                etag = $1,
+                last_modified = $2,
+                ttl_minutes = $3,
+                skip_hours = $4,
+                skip_days = $5,
+                next_fetch_at = $6
+            WHERE id = $7
+            "#,
+        )
+        .bind(etag)
+        .bind(last_modified)
+        .bind(hints.ttl_minutes)
+        .bind(&hints.skip_hours)
+        .bind(&hints.skip_days)
+        .bind(self.next_fetch_at(feed.refresh_interval_minutes, hints))
+        .bind(feed.id)
+        .execute(conn)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Point a feed at the URL it permanently redirects to, remembering the old one.
+    ///
+    /// The move is skipped when another feed is already subscribed to the new URL.
+    async fn move_feed(&self, pool: &PgPool, feed: &Feed, new_url: &str) -> Result<(), AppError> {
+        let clash = sqlx::query("SELECT id FROM feeds WHERE url = $1 AND id != $2")
+            .bind(new_url)
+            .bind(feed.id)
+            .fetch_optional(pool)
             .await
             .map_err(|e| AppError::DatabaseError(e.to_string()))?;
 
+        if clash.is_some() {
+            warn!(
+                "Feed '{}' moved to {}, which another feed already uses; keeping {}",
+                feed.title, new_url, feed.url
+            );
+            return Ok(());
+        }
+
         info!(
-            "Saved {} new articles from feed: {}",
-            saved_count, feed.title
+            "Feed '{}' moved permanently from {} to {}",
+            feed.title, feed.url, new_url
         );
-        Ok(saved_count)
+
+        sqlx::query(
+            r#"
+            UPDATE feeds
+            SET previous_urls = array_append(previous_urls, url), url = $1, updated_at = NOW()
+            WHERE id = $2
+            "#,
+        )
+        .bind(new_url)
+        .bind(feed.id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Record a failed fetch and back off exponentially.
+    ///
+    /// The feed is deactivated once it has failed `max_consecutive_failures` times in a row.
+    async fn record_failure(
+        &self,
+        pool: &PgPool,
+        feed: &Feed,
+        error: &AppError,
+    ) -> Result<(), AppError> {
+        let failures = feed.consecutive_failures + 1;
+        let interval = feed
+            .refresh_interval_minutes
+            .map_or(self.scheduler.default_interval_minutes, i64::from);
+        let backoff =
+            scheduler::backoff_minutes(interval, failures, self.scheduler.max_backoff_minutes);
+        let next_fetch_at = scheduler::next_fetch_at(
+            Utc::now(),
+            backoff,
+            &stored_hints(feed),
+            scheduler::random_jitter(self.scheduler.max_jitter_seconds),
+        );
+
+        let max_failures = self.scheduler.max_consecutive_failures;
+        let disabled_reason = (max_failures > 0 && failures >= max_failures).then(|| {
+            format!(
+                "Deactivated after {} consecutive failed refreshes, last error: {}",
+                failures, error
+            )
+        });
+
+        match &disabled_reason {
+            Some(reason) => warn!("Feed '{}': {}", feed.title, reason),
+            None => warn!(
+                "Feed '{}' failed {} times in a row, retrying in {} minutes",
+                feed.title, failures, backoff
+            ),
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE feeds
+            SET
+                last_error = $1,
+                last_error_at = NOW(),
+                consecutive_failures = $2,
+                next_fetch_at = $3,
+                active = active AND $4::TEXT IS NULL,
+                disabled_reason = COALESCE($4, disabled_reason)
+            WHERE id = $5
+            "#,
+        )
+        .bind(error.to_string())
+        .bind(failures)
+        .bind(next_fetch_at)
+        .bind(disabled_reason)
+        .bind(feed.id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Push a feed's next refresh a full interval out unless it is already scheduled
+    pub async fn reschedule(&self, pool: &PgPool, feed_id: Uuid) -> Result<(), AppError> {
+        let feed = sqlx::query(
+            r#"
+            SELECT refresh_interval_minutes, ttl_minutes, skip_hours, skip_days
+            FROM feeds WHERE id = $1
+            "#,
+        )
+        .bind(feed_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound(format!("Feed with ID {} not found", feed_id)))?;
+
+        let hints = CacheHints {
+            ttl_minutes: feed.get("ttl_minutes"),
+            skip_hours: feed.get("skip_hours"),
+            skip_days: feed.get("skip_days"),
+        };
+        let next_fetch_at = self.next_fetch_at(feed.get("refresh_interval_minutes"), &hints);
+
+        // A failed fetch has already set its backoff, keep that
+        sqlx::query(
+            r#"
+            UPDATE feeds SET next_fetch_at = $1
+            WHERE id = $2 AND (next_fetch_at IS NULL OR next_fetch_at <= NOW())
+            "#,
+        )
+        .bind(next_fetch_at)
+        .bind(feed_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// When the feed should be refreshed next, given its interval and hints
+    fn next_fetch_at(
+        &self,
+        refresh_interval_minutes: Option<i32>,
+        hints: &CacheHints,
+    ) -> DateTime<Utc> {
+        let interval =
+            refresh_interval_minutes.map_or(self.scheduler.default_interval_minutes, i64::from);
+        scheduler::next_fetch_at(
+            Utc::now(),
+            interval,
+            hints,
+            scheduler::random_jitter(self.scheduler.max_jitter_seconds),
+        )
+    }
+
+    /// Refresh several feeds concurrently.
+    ///
+    /// At most `max_concurrent_fetches` refreshes run at once, and no more than
+    /// `max_concurrent_per_host` of them hit the same host. Results come back in
+    /// the order the feeds were given.
+    pub async fn refresh_feeds(
+        &self,
+        pool: &PgPool,
+        feeds: Vec<FeedRef>,
+    ) -> Vec<(FeedRef, Result<SavedEntries, AppError>)> {
+        let global = Semaphore::new(self.settings.max_concurrent_fetches.max(1));
+        let mut hosts: HashMap<String, Semaphore> = HashMap::new();
+        for feed in &feeds {
+            hosts
+                .entry(host_key(&feed.url))
+                .or_insert_with(|| Semaphore::new(self.settings.max_concurrent_per_host.max(1)));
+        }
+
+        let refreshes = feeds.into_iter().map(|feed| {
+            let global = &global;
+            let hosts = &hosts;
+            async move {
+                // Wait for the host first so queued feeds don't hold global slots
+                let _host_permit = match hosts.get(&host_key(&feed.url)) {
+                    Some(host) => host.acquire().await.ok(),
+                    None => None,
+                };
+                let _permit = global.acquire().await.ok();
+
+                let result = self.refresh_feed(pool, feed.id).await;
+                (feed, result)
+            }
+        });
+
+        join_all(refreshes).await
     }
 
     /// Refresh all active feeds
@@ -205,7 +817,12 @@ impl FeedFetcher {
     ) -> Result<serde_json::Value, AppError> {
         // Get all active feeds
         let active_feeds =
-            sqlx::query("SELECT id, title FROM feeds WHERE active = true ORDER BY title")
+            sqlx::query("SELECT id, title, url FROM feeds WHERE active = true ORDER BY title")
+                .map(|row: sqlx::postgres::PgRow| FeedRef {
+                    id: row.get("id"),
+                    title: row.get("title"),
+                    url: row.get("url"),
+                })
                 .fetch_all(pool)
                 .await
                 .map_err(|e| {
@@ -227,29 +844,27 @@ impl FeedFetcher {
         let mut total_articles = 0;
         let mut results = Vec::new();
 
-        for row in active_feeds {
-            let feed_id: Uuid = row.get("id");
-            let feed_title: String = row.get("title");
-
-            match self.refresh_feed(pool, feed_id).await {
-                Ok(count) => {
+        for (feed, result) in self.refresh_feeds(pool, active_feeds).await {
+            match result {
+                Ok(saved) => {
+                    let count = saved.inserted.len();
                     total_articles += count;
                     results.push(serde_json::json!({
-                        "feed_id": feed_id.to_string(),
-                        "feed_title": feed_title,
+                        "feed_id": feed.id.to_string(),
+                        "feed_title": feed.title,
                         "articles_added": count,
                         "success": true
                     }));
                     info!(
                         "Successfully refreshed feed '{}': {} articles",
-                        feed_title, count
+                        feed.title, count
                     );
                 }
                 Err(e) => {
-                    error!("Failed to refresh feed '{}': {:?}", feed_title, e);
+                    error!("Failed to refresh feed '{}': {:?}", feed.title, e);
                     results.push(serde_json::json!({
-                        "feed_id": feed_id.to_string(),
-                        "feed_title": feed_title,
+                        "feed_id": feed.id.to_string(),
+                        "feed_title": feed.title,
                         "articles_added": 0,
                         "success": false,
                         "error": e.to_string()
@@ -273,3 +888,543 @@ impl FeedFetcher {
         }))
     }
 }
+
+/// Media attached to an entry, from `media:` objects, RSS enclosures and Atom enclosure links
+fn entry_enclosures(entry: &feed_rs::model::Entry) -> Vec<Enclosure> {
+    let mut enclosures: Vec<Enclosure> = Vec::new();
+
+    for media in &entry.media {
+        let thumbnail_url = media
+            .thumbnails
+            .first()
+            .map(|thumbnail| thumbnail.image.uri.clone());
+        for content in &media.content {
+            let Some(url) = &content.url else {
+                continue;
+            };
+            enclosures.push(Enclosure {
+                url: url.to_string(),
+                mime_type: content.content_type.as_ref().map(|mime| mime.to_string()),
+                length: content.size.and_then(|size| i64::try_from(size).ok()),
+                duration_seconds: content
+                    .duration
+                    .or(media.duration)
+                    .and_then(|duration| i32::try_from(duration.as_secs()).ok()),
+                thumbnail_url: thumbnail_url.clone(),
+            });
+        }
+    }
+
+    for link in &entry.links {
+        if link.rel.as_deref() == Some("enclosure") {
+            enclosures.push(Enclosure {
+                url: link.href.clone(),
+                mime_type: link.media_type.clone(),
+                length: link.length.and_then(|length| i64::try_from(length).ok()),
+                duration_seconds: None,
+                thumbnail_url: None,
+            });
+        }
+    }
+
+    // The same file can be listed both as an enclosure and as media content
+    let mut seen = std::collections::HashSet::new();
+    enclosures.retain(|enclosure| seen.insert(enclosure.url.clone()));
+    enclosures
+}
+
+/// Named authors of an entry, without duplicates
+fn entry_authors(entry: &feed_rs::model::Entry) -> Vec<Author> {
+    let mut authors: Vec<Author> = Vec::new();
+    for person in &entry.authors {
+        let name = person.name.trim();
+        // feed_rs names authors without a name "unknown"
+        if name.is_empty() || name == "unknown" || authors.iter().any(|author| author.name == name)
+        {
+            continue;
+        }
+        authors.push(Author {
+            name: name.to_string(),
+            email: person.email.clone(),
+            uri: person.uri.clone(),
+        });
+    }
+    authors
+}
+
+/// Category terms of an entry, without duplicates
+fn entry_categories(entry: &feed_rs::model::Entry) -> Vec<String> {
+    let mut categories: Vec<String> = Vec::new();
+    for category in &entry.categories {
+        let term = category.term.trim();
+        if !term.is_empty() && !categories.iter().any(|c| c == term) {
+            categories.push(term.to_string());
+        }
+    }
+    categories
+}
+
+/// Refresh hints remembered from the last full fetch of a feed
+fn stored_hints(feed: &Feed) -> CacheHints {
+    CacheHints {
+        ttl_minutes: feed.ttl_minutes,
+        skip_hours: feed.skip_hours.clone(),
+        skip_days: feed.skip_days.clone(),
+    }
+}
+
+/// Fingerprint of the parts of an entry readers care about
+pub fn content_hash(title: &str, content: &str) -> String {
+    format!("{:x}", Sha256::digest(format!("{}\n{}", title, content)))
+}
+
+/// The date an entry is listed under and where it comes from.
+///
+/// Entries without a usable date get the time they are first seen instead of
+/// one they never had.
+fn entry_date(
+    published: Option<DateTime<Utc>>,
+    updated: Option<DateTime<Utc>>,
+) -> (DateTime<Utc>, DateSource) {
+    let now = Utc::now();
+    let latest = now + chrono::Duration::minutes(MAX_FUTURE_DATE_MINUTES);
+
+    match (published, updated) {
+        (Some(published), _) if published <= latest => (published, DateSource::Published),
+        (_, Some(updated)) if updated <= latest => (updated, DateSource::Updated),
+        (None, None) => (now, DateSource::Missing),
+        _ => (now, DateSource::ClampedFromFuture),
+    }
+}
+
+/// Parser that resolves relative links against the feed URL and leaves the ID
+/// of entries without a link empty rather than inventing a random one
+fn feed_parser(base_uri: &str) -> parser::Parser {
+    parser::Builder::new()
+        .base_uri(Some(base_uri))
+        .id_generator(|links, title, _uri| {
+            if links.is_empty() {
+                String::new()
+            } else {
+                parser::generate_id(links, title, None)
+            }
+        })
+        .build()
+}
+
+/// Group feeds by host for politeness limits, falling back to the raw URL
+fn host_key(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_lowercase()))
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// Read a header as an owned string, ignoring values that are not valid UTF-8
+fn header_value(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use wiremock::{
+        matchers::{header, header_exists, method, path},
+        Mock, MockServer, Request, Respond, ResponseTemplate,
+    };
+
+    const RSS: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Test</title>
+<item><title>Hello</title><link>https://example.com/hello</link></item>
+</channel></rss>"#;
+
+    /// Fetcher settings allowing requests to the local mock servers
+    pub(super) fn test_settings() -> FetcherSettings {
+        FetcherSettings {
+            allowed_hosts: vec!["127.0.0.1".to_string()],
+            ..Default::default()
+        }
+    }
+
+    pub(super) fn test_fetcher() -> FeedFetcher {
+        FeedFetcher::build(
+            test_settings(),
+            SchedulerSettings::default(),
+            WebSubSettings::default(),
+        )
+    }
+
+    pub(super) fn test_feed(url: String) -> Feed {
+        Feed {
+            id: Uuid::new_v4(),
+            title: "Test Feed".to_string(),
+            url,
+            description: None,
+            site_url: None,
+            language: None,
+            icon_url: None,
+            active: true,
+            last_fetched: None,
+            etag: None,
+            last_modified: None,
+            refresh_interval_minutes: None,
+            next_fetch_at: None,
+            ttl_minutes: None,
+            skip_hours: vec![],
+            skip_days: vec![],
+            fetch_full_content: false,
+            last_error: None,
+            last_error_at: None,
+            consecutive_failures: 0,
+            last_success_at: None,
+            disabled_reason: None,
+            previous_urls: vec![],
+            websub_hub: None,
+            websub_lease_expires_at: None,
+            source_type: SourceType::Rss,
+            scrape_selectors: None,
+            http_settings: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// ETag and entries of a freshly fetched document
+    pub(super) fn entries(outcome: FetchOutcome) -> (Option<String>, Vec<FeedEntry>) {
+        match outcome {
+            FetchOutcome::Fetched { articles, etag, .. } => (etag, articles),
+            _ => panic!("expected a fresh document"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_feed_returns_cache_validators() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/rss"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"abc\"")
+                    .insert_header("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+                    .set_body_string(RSS),
+            )
+            .mount(&server)
+            .await;
+
+        let feed = test_feed(format!("{}/rss", server.uri()));
+        match test_fetcher().fetch_feed(&feed).await.unwrap() {
+            FetchOutcome::Fetched {
+                articles,
+                etag,
+                last_modified,
+                ..
+            } => {
+                assert_eq!(articles.len(), 1);
+                assert!(articles[0].enclosures.is_empty());
+                assert_eq!(etag.as_deref(), Some("\"abc\""));
+                assert_eq!(
+                    last_modified.as_deref(),
+                    Some("Wed, 21 Oct 2015 07:28:00 GMT")
+                );
+            }
+            _ => panic!("expected a fresh document"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_feed_sends_conditional_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/rss"))
+            .and(header("If-None-Match", "\"abc\""))
+            .and(header_exists("If-Modified-Since"))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut feed = test_feed(format!("{}/rss", server.uri()));
+        feed.etag = Some("\"abc\"".to_string());
+        feed.last_modified = Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string());
+
+        let outcome = test_fetcher().fetch_feed(&feed).await.unwrap();
+        assert!(matches!(
+            outcome,
+            FetchOutcome::NotModified { moved_to: None }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_feed_reports_permanent_moves() {
+        let server = MockServer::start().await;
+        let redirect =
+            |status, to: &'static str| ResponseTemplate::new(status).insert_header("Location", to);
+        Mock::given(path("/old"))
+            .respond_with(redirect(301, "/older"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/older"))
+            .respond_with(redirect(308, "/rss"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/temporary"))
+            .respond_with(redirect(302, "/old"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/rss"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(RSS))
+            .mount(&server)
+            .await;
+        Mock::given(path("/gone"))
+            .respond_with(ResponseTemplate::new(410))
+            .mount(&server)
+            .await;
+
+        let fetcher = test_fetcher();
+        let moved_to = |outcome| match outcome {
+            FetchOutcome::Fetched { moved_to, .. } => moved_to,
+            _ => panic!("expected a fresh document"),
+        };
+
+        let feed = test_feed(format!("{}/old", server.uri()));
+        assert_eq!(
+            moved_to(fetcher.fetch_feed(&feed).await.unwrap()),
+            Some(format!("{}/rss", server.uri()))
+        );
+
+        // A temporary hop anywhere in the chain keeps the subscribed URL
+        let feed = test_feed(format!("{}/temporary", server.uri()));
+        assert_eq!(moved_to(fetcher.fetch_feed(&feed).await.unwrap()), None);
+
+        let feed = test_feed(format!("{}/gone", server.uri()));
+        assert!(matches!(
+            fetcher.fetch_feed(&feed).await.unwrap(),
+            FetchOutcome::Gone
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_feed_assigns_stable_guids() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/rss"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Test</title>
+<item><title>With guid</title><guid>tag:example.com,2024:1</guid></item>
+<item><title>No link</title><description>first</description></item>
+<item><title>No link either</title><description>second</description></item>
+<item><title>Linked</title><link>/posts/4</link></item>
+</channel></rss>"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let feed = test_feed(format!("{}/rss", server.uri()));
+        let fetcher = test_fetcher();
+        let guids = |outcome| match outcome {
+            FetchOutcome::Fetched { articles, .. } => articles
+                .into_iter()
+                .map(|entry: FeedEntry| (entry.article.guid, entry.article.url))
+                .collect::<Vec<_>>(),
+            _ => panic!("expected a fresh document"),
+        };
+
+        let first = guids(fetcher.fetch_feed(&feed).await.unwrap());
+        let second = guids(fetcher.fetch_feed(&feed).await.unwrap());
+
+        assert_eq!(first, second);
+        assert_eq!(first[0].0, "tag:example.com,2024:1");
+        assert_ne!(first[1].0, first[2].0);
+        assert!(first[1].0.starts_with("sha256:"));
+        assert_eq!(first[1].1, "");
+        assert_eq!(first[3].1, format!("{}/posts/4", server.uri()));
+    }
+
+    #[test]
+    fn test_entry_enclosures() {
+        let xml = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+<channel><title>Podcast</title>
+<item><title>Episode 1</title><guid>ep1</guid>
+<enclosure url="https://cdn.example.com/ep1.mp3" length="123456" type="audio/mpeg"/>
+<itunes:duration>01:02:03</itunes:duration>
+</item>
+<item><title>Video</title><guid>v1</guid>
+<media:group>
+<media:content url="https://video.example.com/v1.mp4" type="video/mp4" duration="90"/>
+<media:thumbnail url="https://video.example.com/v1.jpg"/>
+<media:description>A talk about feeds</media:description>
+</media:group>
+</item>
+</channel></rss>"#;
+
+        let feed = parser::parse(xml.as_bytes()).unwrap();
+        let episode = entry_enclosures(&feed.entries[0]);
+        assert_eq!(episode.len(), 1);
+        assert_eq!(episode[0].url, "https://cdn.example.com/ep1.mp3");
+        assert_eq!(episode[0].mime_type.as_deref(), Some("audio/mpeg"));
+        assert_eq!(episode[0].length, Some(123456));
+        assert_eq!(episode[0].duration_seconds, Some(3723));
+
+        let video = entry_enclosures(&feed.entries[1]);
+        assert_eq!(
+            video,
+            vec![Enclosure {
+                url: "https://video.example.com/v1.mp4".to_string(),
+                mime_type: Some("video/mp4".to_string()),
+                length: None,
+                duration_seconds: Some(90),
+                thumbnail_url: Some("https://video.example.com/v1.jpg".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_entry_authors_and_categories() {
+        let xml = r#"<?xml version="1.0"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>News</title><id>urn:news</id>
+<entry><title>Column</title><id>urn:news:1</id>
+<author><name> Jane Doe </name><email>jane@example.com</email></author>
+<author><name>Jane Doe</name></author>
+<author><name></name></author>
+<category term="Politics"/><category term="politics"/><category term="Politics"/>
+</entry></feed>"#;
+
+        let feed = parser::parse(xml.as_bytes()).unwrap();
+        assert_eq!(
+            entry_authors(&feed.entries[0]),
+            vec![Author {
+                name: "Jane Doe".to_string(),
+                email: Some("jane@example.com".to_string()),
+                uri: None,
+            }]
+        );
+        assert_eq!(
+            entry_categories(&feed.entries[0]),
+            vec!["Politics".to_string(), "politics".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_feed_metadata_from_rss() {
+        let xml = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>
+<title> Example Blog </title>
+<atom:link href="https://example.com/rss" rel="self" type="application/rss+xml"/>
+<link>https://example.com/</link>
+<description>Posts about things</description>
+<language>en-us</language>
+<image><url>https://example.com/logo.png</url><title>Example</title><link>https://example.com/</link></image>
+</channel></rss>"#;
+
+        let metadata = FeedMetadata::from_feed(&parser::parse(xml.as_bytes()).unwrap());
+        assert_eq!(
+            metadata,
+            FeedMetadata {
+                title: Some("Example Blog".to_string()),
+                description: Some("Posts about things".to_string()),
+                site_url: Some("https://example.com/".to_string()),
+                language: Some("en-us".to_string()),
+                icon_url: Some("https://example.com/logo.png".to_string()),
+                hub_url: None,
+                self_url: Some("https://example.com/rss".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_entry_date_sources() {
+        let past = Utc::now() - chrono::Duration::days(3);
+        let future = Utc::now() + chrono::Duration::days(3);
+
+        assert_eq!(entry_date(Some(past), None), (past, DateSource::Published));
+        assert_eq!(
+            entry_date(Some(future), Some(past)),
+            (past, DateSource::Updated)
+        );
+
+        let (date, source) = entry_date(Some(future), None);
+        assert_eq!(source, DateSource::ClampedFromFuture);
+        assert!(date < future);
+
+        let (date, source) = entry_date(None, None);
+        assert_eq!(source, DateSource::Missing);
+        assert!(date > past);
+    }
+
+    /// Serves `RSS` slowly, recording the most requests it had in flight at once
+    struct ConcurrencyCounter {
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl Respond for ConcurrencyCounter {
+        fn respond(&self, _request: &Request) -> ResponseTemplate {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+
+            // Leave the count before answering, so a request sent once a
+            // slot frees up is never counted with the one it replaces
+            let leaving = self.in_flight.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(150));
+                leaving.fetch_sub(1, Ordering::SeqCst);
+            });
+
+            ResponseTemplate::new(200)
+                .set_body_string(RSS)
+                .set_delay(std::time::Duration::from_millis(250))
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_refresh_feeds_limits_requests_per_host(pool: PgPool) {
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ConcurrencyCounter {
+                in_flight: Arc::new(AtomicUsize::new(0)),
+                max_in_flight: max_in_flight.clone(),
+            })
+            .expect(5)
+            .mount(&server)
+            .await;
+
+        let mut feeds = Vec::new();
+        for n in 0..5 {
+            let url = format!("{}/feed-{}.xml", server.uri(), n);
+            let id: Uuid =
+                sqlx::query("INSERT INTO feeds (title, url) VALUES ($1, $2) RETURNING id")
+                    .bind(format!("Feed {}", n))
+                    .bind(&url)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+                    .get("id");
+            feeds.push(FeedRef {
+                id,
+                title: format!("Feed {}", n),
+                url,
+            });
+        }
+
+        let fetcher = FeedFetcher::build(
+            FetcherSettings {
+                max_concurrent_fetches: 10,
+                max_concurrent_per_host: 2,
+                ..test_settings()
+            },
+            SchedulerSettings::default(),
+            WebSubSettings::default(),
+        );
+        let results = fetcher.refresh_feeds(&pool, feeds).await;
+
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+}
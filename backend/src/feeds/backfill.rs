@@ -0,0 +1,419 @@
+use super::{feed_parser, FeedFetcher};
+use crate::{
+    error::AppError,
+    models::{Feed, SourceType},
+};
+use feed_rs::model::Feed as RssFeed;
+use reqwest::{StatusCode, Url};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use tracing::info;
+use uuid::Uuid;
+
+/// What a backfill went through
+#[derive(Debug, Default)]
+pub struct Backfill {
+    pub pages_fetched: usize,
+    /// IDs of the articles that were new
+    pub inserted: Vec<Uuid>,
+}
+
+impl FeedFetcher {
+    /// Store the older entries of a feed by walking its history, at most `max_pages`
+    /// documents including the feed itself (`fetcher.max_backfill_pages` by default).
+    ///
+    /// RFC 5005 archives (`prev-archive`) and paged feeds (`next`) are followed when
+    /// the feed links them, otherwise WordPress-style `?paged=N` pages are tried.
+    pub async fn backfill_feed(
+        &self,
+        pool: &PgPool,
+        feed_id: Uuid,
+        max_pages: Option<usize>,
+    ) -> Result<Backfill, AppError> {
+        let limit = self.settings.max_backfill_pages;
+        let max_pages = max_pages.unwrap_or(limit);
+        if !(1..=limit).contains(&max_pages) {
+            return Err(AppError::ValidationError(format!(
+                "max_pages must be between 1 and {}",
+                limit
+            )));
+        }
+
+        let feed = self.load_feed(pool, feed_id).await?;
+        if feed.source_type != SourceType::Rss {
+            return Err(AppError::BadRequest(
+                "Only RSS, Atom and JSON feeds have a history to backfill".to_string(),
+            ));
+        }
+
+        let fetcher = self.for_feed(&feed)?;
+        let backfill = fetcher.walk_history(pool, &feed, max_pages).await;
+        backfill
+    }
+
+    async fn walk_history(
+        &self,
+        pool: &PgPool,
+        feed: &Feed,
+        max_pages: usize,
+    ) -> Result<Backfill, AppError> {
+        let mut backfill = Backfill::default();
+        let mut visited = HashSet::from([feed.url.clone()]);
+        let mut guids = HashSet::new();
+
+        let mut page = self.fetch_history_page(&feed.url).await?.ok_or_else(|| {
+            AppError::FeedParsingError(format!("Feed '{}' was not found", feed.title))
+        })?;
+        // Feeds linking no history are tried as WordPress pages
+        let paged = history_link(&page).is_none();
+
+        loop {
+            let next_link = history_link(&page);
+            let entries = self.rss_to_articles(feed, page)?;
+
+            // A site ignoring `paged` serves the same entries again
+            let unseen = entries
+                .iter()
+                .filter(|entry| guids.insert(entry.article.guid.clone()))
+                .count();
+            if unseen == 0 {
+                break;
+            }
+
+            let saved = self.save_archived_entries(pool, feed, entries).await?;
+            backfill.pages_fetched += 1;
+            backfill.inserted.extend(saved.inserted);
+            if backfill.pages_fetched >= max_pages {
+                break;
+            }
+
+            let next = if paged {
+                paged_url(&feed.url, backfill.pages_fetched + 1)
+            } else {
+                next_link
+            };
+            let Some(next) = next.filter(|next| visited.insert(next.clone())) else {
+                break;
+            };
+            page = match self.fetch_history_page(&next).await? {
+                Some(page) => page,
+                None => break,
+            };
+        }
+
+        info!(
+            "Backfilled {} articles from {} pages of feed: {}",
+            backfill.inserted.len(),
+            backfill.pages_fetched,
+            feed.title
+        );
+        Ok(backfill)
+    }
+
+    /// Download and parse one document of a feed's history, `None` past its end
+    async fn fetch_history_page(&self, url: &str) -> Result<Option<RssFeed>, AppError> {
+        let response = self.get(url).await?;
+
+        if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(AppError::FeedParsingError(format!(
+                "Failed to fetch {}. Status: {}",
+                url,
+                response.status()
+            )));
+        }
+
+        let url = response.url().to_string();
+        let xml = self.read_feed(response).await?;
+        feed_parser(&url)
+            .parse(xml.as_slice())
+            .map(Some)
+            .map_err(|e| AppError::FeedParsingError(format!("Failed to parse {}: {}", url, e)))
+    }
+}
+
+/// The document holding the entries before these ones, an archive or the next page
+fn history_link(feed: &RssFeed) -> Option<String> {
+    let link = |rel: &str| {
+        feed.links
+            .iter()
+            .find(|link| link.rel.as_deref() == Some(rel))
+            .map(|link| link.href.clone())
+    };
+    link("prev-archive").or_else(|| link("next"))
+}
+
+/// `url` with its `paged` query parameter set to `page`
+fn paged_url(url: &str, page: usize) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    let query: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| name != "paged")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(query)
+        .append_pair("paged", &page.to_string());
+    Some(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{feeds::tests::test_fetcher, routes::articles::has_known_date};
+    use sqlx::Row;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    /// An RSS document with `items`, linking `prev_archive` as its archive
+    fn rss(items: &str, prev_archive: Option<&str>) -> String {
+        let link = prev_archive
+            .map(|href| format!(r#"<atom:link rel="prev-archive" href="{}"/>"#, href))
+            .unwrap_or_default();
+        format!(
+            r#"<?xml version="1.0"?><rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel><title>Test</title>{}{}</channel></rss>"#,
+            link, items
+        )
+    }
+
+    async fn mount(server: &MockServer, route: &str, status: u16, body: String) {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .respond_with(ResponseTemplate::new(status).set_body_string(body))
+            .mount(server)
+            .await;
+    }
+
+    /// Paths and queries the server was asked for, in order
+    async fn requested(server: &MockServer) -> Vec<String> {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| match request.url.query() {
+                Some(query) => format!("{}?{}", request.url.path(), query),
+                None => request.url.path().to_string(),
+            })
+            .collect()
+    }
+
+    async fn insert_feed(pool: &PgPool, url: String) -> Uuid {
+        sqlx::query("INSERT INTO feeds (title, url) VALUES ('Test', $1) RETURNING id")
+            .bind(url)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+            .get("id")
+    }
+
+    async fn guids(pool: &PgPool, feed_id: Uuid, condition: &str) -> Vec<String> {
+        sqlx::query(&format!(
+            "SELECT guid FROM articles WHERE feed_id = $1 AND {} ORDER BY guid",
+            condition
+        ))
+        .bind(feed_id)
+        .map(|row: sqlx::postgres::PgRow| row.get("guid"))
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_backfilled_undated_entries_have_no_known_date(pool: PgPool) {
+        let server = MockServer::start().await;
+        let archive = format!("{}/archive-1.xml", server.uri());
+        mount(
+            &server,
+            "/feed.xml",
+            200,
+            rss(
+                "<item><guid>new</guid><title>New</title></item>",
+                Some(&archive),
+            ),
+        )
+        .await;
+        mount(
+            &server,
+            "/archive-1.xml",
+            200,
+            rss("<item><guid>old</guid><title>Old</title></item>", None),
+        )
+        .await;
+
+        let fetcher = test_fetcher();
+        let feed_id = insert_feed(&pool, format!("{}/feed.xml", server.uri())).await;
+        fetcher.refresh_feed(&pool, feed_id).await.unwrap();
+        // Found by a later refresh, published around when it was first seen
+        server.reset().await;
+        mount(
+            &server,
+            "/archive-1.xml",
+            200,
+            rss("<item><guid>old</guid><title>Old</title></item>", None),
+        )
+        .await;
+        mount(
+            &server,
+            "/feed.xml",
+            200,
+            rss(
+                "<item><guid>newer</guid><title>Newer</title></item>\
+                 <item><guid>new</guid><title>New</title></item>",
+                Some(&archive),
+            ),
+        )
+        .await;
+        fetcher.refresh_feed(&pool, feed_id).await.unwrap();
+
+        let backfill = fetcher.backfill_feed(&pool, feed_id, None).await.unwrap();
+        assert_eq!(backfill.pages_fetched, 2);
+        assert_eq!(backfill.inserted.len(), 1);
+
+        assert_eq!(
+            guids(&pool, feed_id, "true").await,
+            vec!["new", "newer", "old"]
+        );
+        assert_eq!(
+            guids(&pool, feed_id, &has_known_date("articles")).await,
+            vec!["newer"]
+        );
+    }
+
+    #[test]
+    fn test_paged_url_replaces_the_page() {
+        assert_eq!(
+            paged_url("https://example.com/feed/?lang=en&paged=2", 3).as_deref(),
+            Some("https://example.com/feed/?lang=en&paged=3")
+        );
+        assert_eq!(
+            paged_url("https://example.com/feed/", 2).as_deref(),
+            Some("https://example.com/feed/?paged=2")
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_backfill_follows_archives_up_to_max_pages(pool: PgPool) {
+        let server = MockServer::start().await;
+        for (route, guid, previous) in [
+            ("/feed.xml", "p0", "/archive-1.xml"),
+            ("/archive-1.xml", "p1", "/archive-2.xml"),
+            ("/archive-2.xml", "p2", "/archive-3.xml"),
+            ("/archive-3.xml", "p3", "/archive-4.xml"),
+        ] {
+            let previous = format!("{}{}", server.uri(), previous);
+            let item = format!("<item><guid>{0}</guid><title>{0}</title></item>", guid);
+            mount(&server, route, 200, rss(&item, Some(&previous))).await;
+        }
+
+        let fetcher = test_fetcher();
+        let feed_id = insert_feed(&pool, format!("{}/feed.xml", server.uri())).await;
+        let backfill = fetcher
+            .backfill_feed(&pool, feed_id, Some(3))
+            .await
+            .unwrap();
+
+        assert_eq!(backfill.pages_fetched, 3);
+        assert_eq!(backfill.inserted.len(), 3);
+        assert_eq!(
+            requested(&server).await,
+            vec!["/feed.xml", "/archive-1.xml", "/archive-2.xml"]
+        );
+        assert_eq!(guids(&pool, feed_id, "true").await, vec!["p0", "p1", "p2"]);
+    }
+
+    #[sqlx::test]
+    async fn test_backfill_stops_at_an_archive_it_already_visited(pool: PgPool) {
+        let server = MockServer::start().await;
+        for (route, guid, previous) in [
+            ("/feed.xml", "p0", "/archive-1.xml"),
+            ("/archive-1.xml", "p1", "/archive-2.xml"),
+            ("/archive-2.xml", "p2", "/archive-1.xml"),
+        ] {
+            let previous = format!("{}{}", server.uri(), previous);
+            let item = format!("<item><guid>{0}</guid><title>{0}</title></item>", guid);
+            mount(&server, route, 200, rss(&item, Some(&previous))).await;
+        }
+
+        let fetcher = test_fetcher();
+        let feed_id = insert_feed(&pool, format!("{}/feed.xml", server.uri())).await;
+        let backfill = fetcher.backfill_feed(&pool, feed_id, None).await.unwrap();
+
+        assert_eq!(backfill.pages_fetched, 3);
+        assert_eq!(
+            requested(&server).await,
+            vec!["/feed.xml", "/archive-1.xml", "/archive-2.xml"]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_backfill_stops_at_a_missing_archive(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let server = MockServer::start().await;
+        for status in [404, 410] {
+            let previous = format!("{}/archive-{}.xml", server.uri(), status);
+            mount(
+                &server,
+                &format!("/feed-{}.xml", status),
+                200,
+                rss(
+                    "<item><guid>p0</guid><title>p0</title></item>",
+                    Some(&previous),
+                ),
+            )
+            .await;
+            mount(
+                &server,
+                &format!("/archive-{}.xml", status),
+                status,
+                String::new(),
+            )
+            .await;
+
+            let url = format!("{}/feed-{}.xml", server.uri(), status);
+            let feed_id = insert_feed(&pool, url).await;
+            let backfill = fetcher.backfill_feed(&pool, feed_id, None).await.unwrap();
+
+            assert_eq!(backfill.pages_fetched, 1);
+            assert_eq!(backfill.inserted.len(), 1);
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_backfill_stops_when_pages_repeat_entries(pool: PgPool) {
+        let server = MockServer::start().await;
+        // Only the second page exists, later ones get the feed served again
+        Mock::given(method("GET"))
+            .and(path("/feed.xml"))
+            .and(query_param("paged", "2"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(rss("<item><guid>p2</guid><title>p2</title></item>", None)),
+            )
+            .mount(&server)
+            .await;
+        mount(
+            &server,
+            "/feed.xml",
+            200,
+            rss("<item><guid>p1</guid><title>p1</title></item>", None),
+        )
+        .await;
+
+        let fetcher = test_fetcher();
+        let feed_id = insert_feed(&pool, format!("{}/feed.xml", server.uri())).await;
+        let backfill = fetcher.backfill_feed(&pool, feed_id, None).await.unwrap();
+
+        assert_eq!(backfill.pages_fetched, 2);
+        assert_eq!(
+            requested(&server).await,
+            vec!["/feed.xml", "/feed.xml?paged=2", "/feed.xml?paged=3"]
+        );
+        assert_eq!(guids(&pool, feed_id, "true").await, vec!["p1", "p2"]);
+    }
+}
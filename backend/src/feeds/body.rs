@@ -0,0 +1,272 @@
+use super::FeedFetcher;
+use crate::error::AppError;
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::{header::CONTENT_TYPE, Response};
+use std::borrow::Cow;
+
+/// Bytes at the start of a document searched for an in-document charset declaration
+const SNIFF_BYTES: usize = 1024;
+
+impl FeedFetcher {
+    /// Read a feed document as UTF-8, within the size limit
+    pub(super) async fn read_feed(&self, response: Response) -> Result<Vec<u8>, AppError> {
+        let content_type = content_type(&response);
+        let body = read_body(response, self.settings.max_body_bytes).await?;
+        decode_feed(&body, content_type.as_deref()).map(Cow::into_owned)
+    }
+
+    /// Read an HTML page as text, within the size limit
+    pub(super) async fn read_page(&self, response: Response) -> Result<String, AppError> {
+        let content_type = content_type(&response);
+        let body = read_body(response, self.settings.max_body_bytes).await?;
+        decode_page(&body, content_type.as_deref())
+    }
+}
+
+/// `Content-Type` of a response, read before its body consumes it
+pub(super) fn content_type(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Read a (decompressed) response body, giving up as soon as it grows past `max_bytes`
+pub(super) async fn read_body(
+    mut response: Response,
+    max_bytes: usize,
+) -> Result<Vec<u8>, AppError> {
+    let url = response.url().clone();
+    let too_large = || {
+        AppError::UnreadableDocument(format!(
+            "{} is larger than the {} byte limit",
+            url, max_bytes
+        ))
+    };
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes as u64)
+    {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| AppError::FeedParsingError(format!("Failed to read response: {}", e)))?
+    {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
+/// Transcode an XML or JSON feed to UTF-8.
+///
+/// The charset comes from a byte order mark, the `Content-Type` header or the
+/// XML declaration, in that order, and is guessed from the bytes otherwise.
+pub(super) fn decode_feed<'a>(
+    body: &'a [u8],
+    content_type: Option<&str>,
+) -> Result<Cow<'a, [u8]>, AppError> {
+    let declared = content_type
+        .and_then(charset_parameter)
+        .or_else(|| xml_declaration_encoding(body));
+    let (text, encoding) = decode(body, declared)?;
+    if encoding == UTF_8 && body.len() == text.len() {
+        return Ok(Cow::Borrowed(body));
+    }
+
+    // The parser must not decode the text a second time
+    Ok(Cow::Owned(declare_utf8(&text).into_bytes()))
+}
+
+/// Decode an HTML page, using its `<meta charset>` when the headers name none
+pub(super) fn decode_page(body: &[u8], content_type: Option<&str>) -> Result<String, AppError> {
+    let declared = content_type
+        .and_then(charset_parameter)
+        .or_else(|| meta_charset(body));
+    decode(body, declared).map(|(text, _)| text.into_owned())
+}
+
+fn decode<'a>(
+    body: &'a [u8],
+    declared: Option<&'static Encoding>,
+) -> Result<(Cow<'a, str>, &'static Encoding), AppError> {
+    let (encoding, body) = match Encoding::for_bom(body) {
+        Some((encoding, bom_length)) => (encoding, &body[bom_length..]),
+        None => (declared.unwrap_or_else(|| guess_encoding(body)), body),
+    };
+
+    // Declarations are sometimes wrong, fall back to a guess before giving up
+    let text = encoding
+        .decode_without_bom_handling_and_without_replacement(body)
+        .map(|text| (text, encoding))
+        .or_else(|| {
+            let guess = guess_encoding(body);
+            guess
+                .decode_without_bom_handling_and_without_replacement(body)
+                .map(|text| (text, guess))
+        })
+        .filter(|(text, _)| !text.contains('\0'))
+        .ok_or_else(|| {
+            AppError::UnreadableDocument(format!("The document is not {} text", encoding.name()))
+        })?;
+
+    Ok(text)
+}
+
+fn guess_encoding(body: &[u8]) -> &'static Encoding {
+    let mut detector = EncodingDetector::new();
+    detector.feed(body, true);
+    detector.guess(None, true)
+}
+
+/// Encoding named by the `charset` parameter of a `Content-Type`
+fn charset_parameter(content_type: &str) -> Option<&'static Encoding> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, value)| Encoding::for_label(value.trim().trim_matches('"').as_bytes()))
+}
+
+/// Encoding named in `<?xml version="1.0" encoding="..."?>`
+fn xml_declaration_encoding(body: &[u8]) -> Option<&'static Encoding> {
+    let head = &body[..body.len().min(SNIFF_BYTES)];
+    let declaration = head.strip_prefix(b"<?xml")?;
+    let end = declaration.windows(2).position(|w| w == b"?>")?;
+    attribute_value(&declaration[..end], b"encoding=")
+        .and_then(Encoding::for_label)
+        .map(Encoding::output_encoding)
+}
+
+/// Encoding of `<meta charset="...">` or `<meta http-equiv="Content-Type" content="...; charset=...">`
+fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head = body[..body.len().min(SNIFF_BYTES)].to_ascii_lowercase();
+    // A UTF-16 declaration found in ASCII-compatible bytes is wrong, as browsers assume
+    attribute_value(&head, b"charset=")
+        .and_then(Encoding::for_label)
+        .map(Encoding::output_encoding)
+}
+
+/// Value following `name` up to the closing quote or the end of the token
+fn attribute_value<'a>(text: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    let start = text.windows(name.len()).position(|w| w == name)? + name.len();
+    let value = &text[start..];
+    let value = value
+        .strip_prefix(b"\"")
+        .or_else(|| value.strip_prefix(b"'"))
+        .unwrap_or(value);
+    let end = value
+        .iter()
+        .position(|b| matches!(b, b'"' | b'\'' | b';' | b'>' | b'/' | b' ' | b'?'))
+        .unwrap_or(value.len());
+    Some(&value[..end])
+}
+
+/// Rewrite the encoding of an XML declaration to UTF-8
+fn declare_utf8(text: &str) -> String {
+    let declaration_end = text.strip_prefix("<?xml").and_then(|_| text.find("?>"));
+    let value = declaration_end.and_then(|end| {
+        let start = text[..end].find("encoding=")? + "encoding=".len();
+        let quote = text[start..]
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\''))?;
+        let length = text[start + 1..end].find(quote)?;
+        Some((start + 1, start + 1 + length))
+    });
+
+    match value {
+        Some((start, end)) => format!("{}UTF-8{}", &text[..start], &text[end..]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FetcherSettings, SchedulerSettings, WebSubSettings};
+    use crate::feeds::tests::{test_feed, test_settings};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_fetch_feed_rejects_oversize_documents() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/huge.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                "<rss><channel><title>{}</title></channel></rss>",
+                "x".repeat(4096)
+            )))
+            .mount(&server)
+            .await;
+
+        let fetcher = FeedFetcher::build(
+            FetcherSettings {
+                max_body_bytes: 1024,
+                ..test_settings()
+            },
+            SchedulerSettings::default(),
+            WebSubSettings::default(),
+        );
+        let feed = test_feed(format!("{}/huge.xml", server.uri()));
+        assert!(matches!(
+            fetcher.fetch_feed(&feed).await,
+            Err(AppError::UnreadableDocument(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_feed_transcodes_legacy_charsets() {
+        // "Café" in ISO-8859-1, declared only in the XML declaration
+        let latin1 =
+            b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><rss><title>Caf\xe9</title></rss>";
+        let decoded = decode_feed(latin1, Some("application/rss+xml")).unwrap();
+        assert_eq!(
+            String::from_utf8(decoded.into_owned()).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><rss><title>Café</title></rss>"
+        );
+
+        // The header wins over the document
+        let cp1252 = b"<?xml version=\"1.0\"?><rss><title>\x93Quoted\x94</title></rss>";
+        let decoded = decode_feed(cp1252, Some("text/xml; charset=windows-1252")).unwrap();
+        assert!(String::from_utf8(decoded.into_owned())
+            .unwrap()
+            .contains("“Quoted”"));
+
+        let utf8 = "<rss><title>Café</title></rss>".as_bytes();
+        assert!(matches!(decode_feed(utf8, None).unwrap(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_decode_rejects_binary_documents() {
+        let binary = [
+            0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0, 0, 0x0d,
+        ];
+        assert!(matches!(
+            decode_feed(&binary, Some("application/rss+xml")),
+            Err(AppError::UnreadableDocument(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_page_reads_meta_charset() {
+        let page = b"<html><head><meta charset=\"iso-8859-1\"></head><body>na\xefve</body></html>";
+        assert!(decode_page(page, Some("text/html"))
+            .unwrap()
+            .contains("naïve"));
+    }
+}
@@ -0,0 +1,285 @@
+use super::{body, FeedFetcher, FeedMetadata};
+use crate::{error::AppError, models::FeedCandidate};
+use feed_rs::{model::FeedType, parser};
+use futures::future::join_all;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use tracing::{debug, info};
+
+/// MIME types advertised by `<link rel="alternate">` elements that point at feeds
+const FEED_CONTENT_TYPES: &[&str] = &[
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+    "application/rdf+xml",
+];
+
+/// Locations probed when a page does not advertise any feed
+const FALLBACK_PATHS: &[&str] = &[
+    "/feed",
+    "/rss",
+    "/feed.xml",
+    "/rss.xml",
+    "/atom.xml",
+    "/index.xml",
+    "/feed.json",
+];
+
+/// A feed found behind a URL, with the metadata of its document when discovery
+/// already downloaded it
+pub struct DiscoveredFeed {
+    pub candidate: FeedCandidate,
+    pub metadata: Option<FeedMetadata>,
+}
+
+impl FeedFetcher {
+    /// Find the feeds behind a URL.
+    ///
+    /// A URL that already serves a feed yields itself. An HTML page yields the
+    /// feeds it advertises, or whatever answers at the common fallback paths
+    /// when it advertises none.
+    pub async fn discover_feeds(&self, url: &str) -> Result<Vec<FeedCandidate>, AppError> {
+        let discovered = self.discover(url).await?;
+        Ok(discovered
+            .into_iter()
+            .map(|found| found.candidate)
+            .collect())
+    }
+
+    /// `discover_feeds`, keeping the metadata of the feed documents it read
+    pub async fn discover(&self, url: &str) -> Result<Vec<DiscoveredFeed>, AppError> {
+        let url = Url::parse(url)
+            .map_err(|e| AppError::ValidationError(format!("Invalid URL '{}': {}", url, e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(AppError::ValidationError(format!(
+                "Unsupported URL scheme '{}'",
+                url.scheme()
+            )));
+        }
+
+        info!("Discovering feeds at {}", url);
+
+        let response = self.get(url.as_str()).await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::FeedParsingError(format!(
+                "Failed to fetch page. Status: {}",
+                response.status()
+            )));
+        }
+
+        // Resolve relative links against where redirects actually took us
+        let page_url = response.url().clone();
+        let content_type = body::content_type(&response);
+        let body = body::read_body(response, self.settings.max_body_bytes).await?;
+
+        let feed = body::decode_feed(&body, content_type.as_deref())?;
+        if let Some(found) = parse_candidate(&page_url, &feed) {
+            debug!("{} is a feed itself", page_url);
+            return Ok(vec![found]);
+        }
+
+        let page = body::decode_page(&body, content_type.as_deref())?;
+        let candidates = extract_feed_links(&page, &page_url);
+        if !candidates.is_empty() {
+            return Ok(candidates
+                .into_iter()
+                .map(|candidate| DiscoveredFeed {
+                    candidate,
+                    metadata: None,
+                })
+                .collect());
+        }
+
+        debug!("No advertised feeds at {}, probing common paths", page_url);
+        let probes = FALLBACK_PATHS
+            .iter()
+            .filter_map(|path| page_url.join(path).ok())
+            .map(|url| self.probe_feed(url));
+
+        let mut discovered: Vec<DiscoveredFeed> = Vec::new();
+        for found in join_all(probes).await.into_iter().flatten() {
+            // Several fallback paths often redirect to the same feed
+            if !discovered
+                .iter()
+                .any(|d| d.candidate.url == found.candidate.url)
+            {
+                discovered.push(found);
+            }
+        }
+
+        Ok(discovered)
+    }
+
+    /// Check whether a URL serves a parseable feed
+    async fn probe_feed(&self, url: Url) -> Option<DiscoveredFeed> {
+        let response = self.get(url.as_str()).await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let url = response.url().clone();
+        let body = self.read_feed(response).await.ok()?;
+        parse_candidate(&url, &body)
+    }
+}
+
+/// Describe a document as a feed candidate if `feed_rs` can parse it
+fn parse_candidate(url: &Url, body: &[u8]) -> Option<DiscoveredFeed> {
+    let feed = parser::parse(body).ok()?;
+    let content_type = match feed.feed_type {
+        FeedType::Atom => "application/atom+xml",
+        FeedType::JSON => "application/feed+json",
+        FeedType::RSS1 => "application/rdf+xml",
+        FeedType::RSS0 | FeedType::RSS2 => "application/rss+xml",
+    };
+
+    let metadata = FeedMetadata::from_feed(&feed);
+    Some(DiscoveredFeed {
+        candidate: FeedCandidate {
+            url: url.to_string(),
+            title: feed.title.map(|t| t.content),
+            content_type: Some(content_type.to_string()),
+        },
+        metadata: Some(metadata),
+    })
+}
+
+/// Collect `<link rel="alternate">` feed references from an HTML page
+fn extract_feed_links(html: &str, page_url: &Url) -> Vec<FeedCandidate> {
+    let document = Html::parse_document(html);
+    let link_selector = Selector::parse("link[rel][href]").expect("valid selector");
+    let base_selector = Selector::parse("base[href]").expect("valid selector");
+
+    let base = document
+        .select(&base_selector)
+        .next()
+        .and_then(|base| base.value().attr("href"))
+        .and_then(|href| page_url.join(href).ok())
+        .unwrap_or_else(|| page_url.clone());
+
+    let mut candidates: Vec<FeedCandidate> = Vec::new();
+    for link in document.select(&link_selector) {
+        let element = link.value();
+        let is_alternate = element.attr("rel").is_some_and(|rel| {
+            rel.split_whitespace()
+                .any(|r| r.eq_ignore_ascii_case("alternate"))
+        });
+        let content_type = element
+            .attr("type")
+            .map(|t| t.trim().to_ascii_lowercase())
+            .filter(|t| FEED_CONTENT_TYPES.contains(&t.as_str()));
+
+        let (true, Some(content_type)) = (is_alternate, content_type) else {
+            continue;
+        };
+        let Some(url) = element
+            .attr("href")
+            .and_then(|href| base.join(href.trim()).ok())
+        else {
+            continue;
+        };
+
+        let url = url.to_string();
+        if candidates.iter().any(|c| c.url == url) {
+            continue;
+        }
+
+        candidates.push(FeedCandidate {
+            url,
+            title: element
+                .attr("title")
+                .map(|title| title.trim().to_string())
+                .filter(|title| !title.is_empty()),
+            content_type: Some(content_type),
+        });
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::tests::test_fetcher;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_extract_feed_links() {
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="alternate" type="application/rss+xml" title="Posts" href="/feed.xml">
+            <link rel="alternate" type="application/atom+xml" href="https://other.example/atom">
+            <link rel="alternate" type="text/html" hreflang="fr" href="/fr/">
+        </head><body></body></html>"#;
+        let page = Url::parse("https://example.com/blog/").unwrap();
+
+        let candidates = extract_feed_links(html, &page);
+        assert_eq!(
+            candidates,
+            vec![
+                FeedCandidate {
+                    url: "https://example.com/feed.xml".to_string(),
+                    title: Some("Posts".to_string()),
+                    content_type: Some("application/rss+xml".to_string()),
+                },
+                FeedCandidate {
+                    url: "https://other.example/atom".to_string(),
+                    title: None,
+                    content_type: Some("application/atom+xml".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_discover_feeds_probes_fallback_paths() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html><head></head></html>"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/rss.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Found</title></channel></rss>"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let candidates = test_fetcher()
+            .discover_feeds(&format!("{}/", server.uri()))
+            .await
+            .unwrap();
+
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].url, format!("{}/rss.xml", server.uri()));
+        assert_eq!(candidates[0].title.as_deref(), Some("Found"));
+    }
+
+    #[tokio::test]
+    async fn test_discovering_a_feed_reads_its_metadata_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/feed.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Posts</title>
+                <description>All the posts</description></channel></rss>"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut discovered = test_fetcher()
+            .discover(&format!("{}/feed.xml", server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(discovered.len(), 1);
+        let metadata = discovered.remove(0).metadata.unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Posts"));
+        assert_eq!(metadata.description.as_deref(), Some("All the posts"));
+    }
+}
@@ -0,0 +1,286 @@
+use super::FeedFetcher;
+use crate::{content::html_to_text, error::AppError, models::Article};
+use scraper::{ElementRef, Html, Selector};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+/// Elements whose content is never part of the article body
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form", "button",
+];
+
+/// Class/ID fragments that mark page furniture rather than content
+const UNLIKELY_HINTS: &[&str] = &[
+    "comment",
+    "sidebar",
+    "footer",
+    "menu",
+    "share",
+    "social",
+    "related",
+    "promo",
+    "advert",
+    "cookie",
+    "newsletter",
+    "subscribe",
+];
+
+/// Block elements copied into the extracted body
+const BLOCK_SELECTOR: &str = "p, h1, h2, h3, h4, h5, h6, li, blockquote, pre";
+
+/// Extractions shorter than this are assumed to have missed the article
+const MIN_EXTRACTED_CHARS: usize = 200;
+
+impl FeedFetcher {
+    /// Replace an article's content with the body extracted from its web page.
+    ///
+    /// The page is requested with the HTTP settings of the article's feed, and the
+    /// feed-provided text is kept in `excerpt` the first time this runs.
+    pub async fn extract_full_content(
+        &self,
+        pool: &PgPool,
+        article_id: Uuid,
+    ) -> Result<Article, AppError> {
+        let http_settings: Option<Vec<u8>> = sqlx::query(
+            "SELECT f.http_settings FROM articles a JOIN feeds f ON f.id = a.feed_id WHERE a.id = $1",
+        )
+        .bind(article_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound(format!("Article with ID {} not found", article_id)))?
+        .get("http_settings");
+
+        let fetcher = self.with_sealed_http_settings(http_settings.as_deref())?;
+        let article = fetcher.extract_article_page(pool, article_id).await;
+        article
+    }
+
+    /// `extract_full_content` with a fetcher already set up for the article's feed
+    pub(super) async fn extract_article_page(
+        &self,
+        pool: &PgPool,
+        article_id: Uuid,
+    ) -> Result<Article, AppError> {
+        let url: String = sqlx::query("SELECT url FROM articles WHERE id = $1")
+            .bind(article_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| AppError::NotFound(format!("Article with ID {} not found", article_id)))?
+            .get("url");
+
+        if url.is_empty() {
+            return Err(AppError::BadRequest(format!(
+                "Article {} has no link to extract content from",
+                article_id
+            )));
+        }
+
+        info!(
+            "Extracting full content for article {} ({})",
+            article_id, url
+        );
+
+        let response = self.get(&url).await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::FeedParsingError(format!(
+                "Failed to fetch article. Status: {}",
+                response.status()
+            )));
+        }
+
+        let html = self.read_page(response).await?;
+
+        let content = extract_main_content(&html).ok_or_else(|| {
+            AppError::FeedParsingError(format!("Could not find the article body at {}", url))
+        })?;
+
+        sqlx::query(
+            r#"
+            UPDATE articles
+            SET excerpt = COALESCE(excerpt, content), content = $1, content_text = $2,
+                updated_at = NOW()
+            WHERE id = $3
+            RETURNING *
+            "#,
+        )
+        .bind(&content)
+        .bind(html_to_text(&content))
+        .bind(article_id)
+        .map(|row: sqlx::postgres::PgRow| Article {
+            id: row.get("id"),
+            title: row.get("title"),
+            url: row.get("url"),
+            guid: row.get("guid"),
+            feed_id: row.get("feed_id"),
+            content: row.get("content"),
+            content_text: row.get("content_text"),
+            excerpt: row.get("excerpt"),
+            published_at: row.get("published_at"),
+            source_updated_at: row.get("source_updated_at"),
+            first_seen_at: row.get("first_seen_at"),
+            date_source: row.get("date_source"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        })
+        .fetch_one(pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))
+    }
+}
+
+/// Find the main body of a web page, readability style.
+///
+/// Paragraphs vote for their parent and grandparent containers, the container
+/// with the best score (discounted by how much of it is link text) wins and its
+/// block elements are rebuilt as plain, escaped HTML.
+pub fn extract_main_content(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let paragraphs = Selector::parse("p").expect("valid selector");
+
+    let mut scores = HashMap::new();
+    for paragraph in document.select(&paragraphs) {
+        if is_unlikely(paragraph) {
+            continue;
+        }
+
+        let text = element_text(paragraph);
+        let length = text.chars().count();
+        if length < 25 {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f64 + (length / 100).min(3) as f64;
+        let Some(parent) = paragraph.parent() else {
+            continue;
+        };
+        *scores.entry(parent.id()).or_insert(0.0) += score;
+        if let Some(grandparent) = parent.parent() {
+            *scores.entry(grandparent.id()).or_insert(0.0) += score / 2.0;
+        }
+    }
+
+    let best = scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = ElementRef::wrap(document.tree.get(id)?)?;
+            Some((element, score * (1.0 - link_density(element))))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))?
+        .0;
+
+    let blocks = Selector::parse(BLOCK_SELECTOR).expect("valid selector");
+    let mut body = String::new();
+    let mut length = 0;
+    for block in best.select(&blocks) {
+        // Nested blocks (a paragraph inside a quote) are emitted by their outermost block
+        let nested = block
+            .ancestors()
+            .take_while(|node| node.id() != best.id())
+            .filter_map(ElementRef::wrap)
+            .any(|ancestor| blocks.matches(&ancestor));
+        if nested || is_unlikely(block) {
+            continue;
+        }
+
+        let text = element_text(block);
+        if text.is_empty() {
+            continue;
+        }
+
+        let name = block.value().name();
+        length += text.chars().count();
+        body.push_str(&format!("<{0}>{1}</{0}>\n", name, escape_html(&text)));
+    }
+
+    debug!("Extracted {} characters of article text", length);
+    (length >= MIN_EXTRACTED_CHARS).then_some(body)
+}
+
+/// Whether an element sits inside page furniture such as navigation or comments
+fn is_unlikely(element: ElementRef) -> bool {
+    std::iter::once(element)
+        .chain(element.ancestors().filter_map(ElementRef::wrap))
+        .any(|element| {
+            let value = element.value();
+            let hints = format!(
+                "{} {}",
+                value.attr("class").unwrap_or_default(),
+                value.id().unwrap_or_default()
+            )
+            .to_lowercase();
+            SKIPPED_ELEMENTS.contains(&value.name())
+                || UNLIKELY_HINTS.iter().any(|hint| hints.contains(hint))
+        })
+}
+
+/// Share of an element's text that is inside links
+fn link_density(element: ElementRef) -> f64 {
+    let links = Selector::parse("a").expect("valid selector");
+    let total = element_text(element).chars().count();
+    if total == 0 {
+        return 1.0;
+    }
+    let linked: usize = element
+        .select(&links)
+        .map(|link| element_text(link).chars().count())
+        .sum();
+    (linked as f64 / total as f64).min(1.0)
+}
+
+/// Visible text of an element with whitespace collapsed
+fn element_text(element: ElementRef) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(super) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_main_content_skips_page_furniture() {
+        let html = r#"<html><body>
+            <nav><p>Home, About, Contact, and all the other links in the menu bar</p></nav>
+            <div class="content"><article>
+                <h1>Big News</h1>
+                <p>The first paragraph of the story explains, in some detail, what happened today.</p>
+                <p>A second paragraph adds context, quotes, and background for the curious reader.</p>
+                <blockquote><p>"Quoted words," said someone, "matter a great deal here & now."</p></blockquote>
+                <p>The closing paragraph wraps up the story and hints at what comes next, maybe.</p>
+            </article></div>
+            <div class="comments"><p>Great article, thanks for sharing this with all of us here!</p></div>
+        </body></html>"#;
+
+        let content = extract_main_content(html).unwrap();
+        assert!(content.starts_with("<h1>Big News</h1>\n<p>The first paragraph"));
+        assert!(content.contains("<blockquote>&quot;Quoted words,&quot; said someone"));
+        assert!(content.contains("here &amp; now"));
+        assert!(!content.contains("menu bar"));
+        assert!(!content.contains("Great article"));
+    }
+
+    #[test]
+    fn test_extract_main_content_rejects_thin_pages() {
+        assert_eq!(
+            extract_main_content(
+                "<html><body><p>Too short to be an article body.</p></body></html>"
+            ),
+            None
+        );
+    }
+}
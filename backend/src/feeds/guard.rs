@@ -0,0 +1,297 @@
+use super::MAX_REDIRECTS;
+use crate::{config::FetcherSettings, error::AppError};
+use hyper::client::connect::dns::Name;
+use ipnet::IpNet;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    redirect::Policy,
+    Url,
+};
+use std::{error::Error, net::IpAddr, sync::Arc};
+use thiserror::Error;
+
+/// Loopback, private, link-local (cloud metadata lives at 169.254.169.254), shared,
+/// multicast and reserved ranges
+const INTERNAL_NETWORKS: &[&str] = &[
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.168.0.0/16",
+    "198.18.0.0/15",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "::/128",
+    "::1/128",
+    "fc00::/7",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+/// A request to an address inside our own network
+#[derive(Debug, Error)]
+#[error("{0} is an internal address")]
+pub(super) struct BlockedAddress(String);
+
+/// Keeps outbound requests away from internal addresses.
+///
+/// Host names are checked once resolved, as the resolver of every client, so a
+/// name cannot point somewhere else between the check and the connection.
+#[derive(Clone)]
+pub(super) struct AddressGuard {
+    inner: Arc<Allowlist>,
+}
+
+struct Allowlist {
+    internal: Vec<IpNet>,
+    hosts: Vec<String>,
+    networks: Vec<IpNet>,
+}
+
+impl AddressGuard {
+    pub(super) fn new(settings: &FetcherSettings) -> Self {
+        let (networks, hosts): (Vec<_>, Vec<_>) = settings
+            .allowed_hosts
+            .iter()
+            .map(|entry| entry.trim().trim_end_matches('.').to_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .partition(|entry| network(entry).is_some());
+
+        Self {
+            inner: Arc::new(Allowlist {
+                internal: INTERNAL_NETWORKS
+                    .iter()
+                    .map(|net| net.parse().expect("valid network"))
+                    .collect(),
+                hosts,
+                networks: networks.iter().filter_map(|entry| network(entry)).collect(),
+            }),
+        }
+    }
+
+    fn allows_ip(&self, ip: IpAddr) -> bool {
+        let ip = unmapped(ip);
+        self.inner.networks.iter().any(|net| net.contains(&ip))
+            || !self.inner.internal.iter().any(|net| net.contains(&ip))
+    }
+
+    fn allows_host(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        self.inner.hosts.contains(&host)
+    }
+
+    /// Reject URLs naming an internal IP address, which skip the resolver
+    pub(super) fn check(&self, url: &Url) -> Result<(), BlockedAddress> {
+        let Some(ip) = url.host_str().and_then(|host| {
+            host.trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .ok()
+        }) else {
+            return Ok(());
+        };
+        if self.allows_ip(ip) {
+            Ok(())
+        } else {
+            Err(BlockedAddress(ip.to_string()))
+        }
+    }
+
+    /// `check` for a URL that has not been parsed yet, invalid URLs are left to reqwest
+    pub(super) fn check_url(&self, url: &str) -> Result<(), AppError> {
+        match Url::parse(url) {
+            Ok(url) => self.check(&url).map_err(blocked),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Reject proxies missing from the allowlist.
+    ///
+    /// A proxy connects to the feed on our behalf, so the resolver never sees where
+    /// requests really go and the proxy itself has to be trusted.
+    pub(super) fn check_proxy(&self, proxy: &Url) -> Result<(), AppError> {
+        let host = proxy
+            .host_str()
+            .unwrap_or_default()
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let allowed = match host.parse::<IpAddr>() {
+            Ok(ip) => {
+                let ip = unmapped(ip);
+                self.inner.networks.iter().any(|net| net.contains(&ip))
+            }
+            Err(_) => self.allows_host(host),
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(AppError::BlockedRequest(format!(
+                "Proxy {} is not allowed, add it to fetcher.allowed_hosts to use it",
+                host
+            )))
+        }
+    }
+
+    /// Redirect policy checking every hop, with reqwest's default limit
+    pub(super) fn redirect_policy(&self) -> Policy {
+        let guard = self.clone();
+        Policy::custom(move |attempt| {
+            if attempt.previous().len() > MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if let Err(e) = guard.check(attempt.url()) {
+                attempt.error(e)
+            } else {
+                attempt.follow()
+            }
+        })
+    }
+}
+
+impl Resolve for AddressGuard {
+    fn resolve(&self, name: Name) -> Resolving {
+        let guard = self.clone();
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<_> = tokio::net::lookup_host((host, 0)).await?.collect();
+            if guard.allows_host(host) {
+                return Ok(Box::new(addrs.into_iter()) as Addrs);
+            }
+
+            let allowed: Vec<_> = addrs
+                .into_iter()
+                .filter(|addr| guard.allows_ip(addr.ip()))
+                .collect();
+            if allowed.is_empty() {
+                return Err(
+                    Box::new(BlockedAddress(host.to_string())) as Box<dyn Error + Send + Sync>
+                );
+            }
+            Ok(Box::new(allowed.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// IPv4 addresses written as IPv6 must not get around the IPv4 ranges
+fn unmapped(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// An allowlist entry as a network, single addresses included
+fn network(entry: &str) -> Option<IpNet> {
+    entry
+        .parse()
+        .ok()
+        .or_else(|| entry.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+pub(super) fn blocked(e: BlockedAddress) -> AppError {
+    AppError::BlockedRequest(format!(
+        "{}, add it to fetcher.allowed_hosts to fetch it",
+        e
+    ))
+}
+
+/// Describe a failed request, telling blocked addresses apart from other failures
+pub(super) fn request_error(context: &str, e: reqwest::Error) -> AppError {
+    let mut source = e.source();
+    while let Some(cause) = source {
+        if let Some(blocked_address) = cause.downcast_ref::<BlockedAddress>() {
+            return blocked(BlockedAddress(blocked_address.0.clone()));
+        }
+        source = cause.source();
+    }
+    AppError::FeedParsingError(format!("{}: {}", context, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SchedulerSettings, WebSubSettings};
+    use crate::feeds::{tests::test_feed, FeedFetcher};
+
+    #[tokio::test]
+    async fn test_fetch_feed_blocks_internal_hosts() {
+        let fetcher = FeedFetcher::build(
+            FetcherSettings::default(),
+            SchedulerSettings::default(),
+            WebSubSettings::default(),
+        );
+
+        // Addresses are checked before connecting, host names once resolved
+        for url in ["http://127.0.0.1:9/feed.xml", "http://localhost:9/feed.xml"] {
+            assert!(
+                matches!(
+                    fetcher.fetch_feed(&test_feed(url.to_string())).await,
+                    Err(AppError::BlockedRequest(_))
+                ),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_internal_addresses_are_blocked() {
+        let guard = AddressGuard::new(&FetcherSettings {
+            allowed_hosts: vec!["10.1.0.0/16".to_string(), "intranet.local".to_string()],
+            ..Default::default()
+        });
+
+        for url in [
+            "http://127.0.0.1:5432/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://192.168.1.1/feed",
+            "http://[::1]/feed",
+            "http://[::ffff:10.0.0.1]/feed",
+            "http://[fd00:ec2::254]/",
+            "http://10.2.0.1/",
+        ] {
+            assert!(guard.check(&Url::parse(url).unwrap()).is_err(), "{}", url);
+        }
+        for url in [
+            "https://93.184.216.34/feed",
+            "http://10.1.2.3/feed",
+            "https://example.com/feed",
+        ] {
+            assert!(guard.check(&Url::parse(url).unwrap()).is_ok(), "{}", url);
+        }
+        assert!(guard.allows_host("Intranet.local."));
+    }
+
+    #[test]
+    fn test_proxies_must_be_allowlisted() {
+        let guard = AddressGuard::new(&FetcherSettings {
+            allowed_hosts: vec!["10.1.0.0/16".to_string(), "proxy.internal".to_string()],
+            ..Default::default()
+        });
+
+        for proxy in ["http://proxy.internal:3128", "http://user:pw@10.1.2.3:8080"] {
+            assert!(
+                guard.check_proxy(&Url::parse(proxy).unwrap()).is_ok(),
+                "{}",
+                proxy
+            );
+        }
+        // Public names may resolve anywhere, internal addresses need allowing
+        for proxy in [
+            "http://proxy.example.com:3128",
+            "http://127.0.0.1:3128",
+            "http://[::ffff:10.2.0.1]:3128",
+        ] {
+            assert!(
+                matches!(
+                    guard.check_proxy(&Url::parse(proxy).unwrap()),
+                    Err(AppError::BlockedRequest(_))
+                ),
+                "{}",
+                proxy
+            );
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use super::{FeedFetcher, DEFAULT_USER_AGENT};
+use crate::{
+    error::AppError,
+    models::{Feed, FeedHttpSettings, HttpAuth},
+};
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE},
+    redirect::Policy,
+    Client, Proxy, Url,
+};
+use std::{borrow::Cow, sync::Arc, time::Duration};
+use tracing::warn;
+
+/// Bytes of the random nonce stored in front of each encrypted settings blob
+const NONCE_LEN: usize = 12;
+
+/// Longest request timeout a feed can ask for
+const MAX_TIMEOUT_SECONDS: u64 = 300;
+
+/// Cipher for feed HTTP settings from a base64-encoded key, `None` when no usable key is set
+pub(super) fn settings_cipher(secret_key: &str) -> Option<Aes256Gcm> {
+    let secret_key = secret_key.trim();
+    if secret_key.is_empty() {
+        return None;
+    }
+
+    let cipher = BASE64
+        .decode(secret_key)
+        .ok()
+        .and_then(|key| Aes256Gcm::new_from_slice(&key).ok());
+    if cipher.is_none() {
+        warn!("fetcher.secret_key is not a base64-encoded 32-byte key, feed HTTP settings are disabled");
+    }
+    cipher
+}
+
+impl FeedFetcher {
+    /// Encrypt HTTP settings for storage, empty settings are stored as nothing
+    pub fn seal_http_settings(
+        &self,
+        settings: &FeedHttpSettings,
+    ) -> Result<Option<Vec<u8>>, AppError> {
+        if *settings == FeedHttpSettings::default() {
+            return Ok(None);
+        }
+        // Reject settings that cannot be applied before storing them
+        self.with_http_settings(settings)?;
+
+        let cipher = self.cipher.as_ref().ok_or_else(|| {
+            AppError::ValidationError(
+                "Feed HTTP settings need fetcher.secret_key to be configured".to_string(),
+            )
+        })?;
+        let plaintext = serde_json::to_vec(settings)
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher.encrypt(&nonce, plaintext.as_ref()).map_err(|_| {
+            AppError::InternalServerError("Failed to encrypt feed HTTP settings".to_string())
+        })?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(Some(sealed))
+    }
+
+    fn open_http_settings(&self, sealed: &[u8]) -> Result<FeedHttpSettings, AppError> {
+        let failed = || {
+            AppError::InternalServerError(
+                "Failed to decrypt feed HTTP settings, was fetcher.secret_key changed?".to_string(),
+            )
+        };
+        let cipher = self.cipher.as_ref().ok_or_else(failed)?;
+        if sealed.len() < NONCE_LEN {
+            return Err(failed());
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| failed())?;
+        serde_json::from_slice(&plaintext).map_err(|_| failed())
+    }
+
+    /// A fetcher for a feed, with its stored HTTP settings applied when it has any
+    pub fn for_feed(&self, feed: &Feed) -> Result<Cow<'_, FeedFetcher>, AppError> {
+        self.with_sealed_http_settings(feed.http_settings.as_deref())
+    }
+
+    /// A fetcher with encrypted HTTP settings applied, or this one without settings
+    pub fn with_sealed_http_settings(
+        &self,
+        sealed: Option<&[u8]>,
+    ) -> Result<Cow<'_, FeedFetcher>, AppError> {
+        match sealed {
+            Some(sealed) => {
+                let settings = self.open_http_settings(sealed)?;
+                Ok(Cow::Owned(self.with_http_settings(&settings)?))
+            }
+            None => Ok(Cow::Borrowed(self)),
+        }
+    }
+
+    /// A fetcher whose requests use a feed's credentials, headers and options
+    pub fn with_http_settings(&self, settings: &FeedHttpSettings) -> Result<FeedFetcher, AppError> {
+        let timeout_seconds = settings
+            .timeout_seconds
+            .unwrap_or(self.settings.request_timeout_seconds);
+        if !(1..=MAX_TIMEOUT_SECONDS).contains(&timeout_seconds) {
+            return Err(AppError::ValidationError(format!(
+                "timeout_seconds must be between 1 and {}",
+                MAX_TIMEOUT_SECONDS
+            )));
+        }
+
+        let user_agent = settings
+            .user_agent
+            .as_deref()
+            .map(str::trim)
+            .filter(|user_agent| !user_agent.is_empty())
+            .unwrap_or(DEFAULT_USER_AGENT);
+        let proxy = settings
+            .proxy
+            .as_deref()
+            .map(|proxy| {
+                let invalid = |e: &dyn std::fmt::Display| {
+                    AppError::ValidationError(format!("Invalid proxy: {}", e))
+                };
+                let url = Url::parse(proxy).map_err(|e| invalid(&e))?;
+                self.guard.check_proxy(&url)?;
+                Proxy::all(url).map_err(|e| invalid(&e))
+            })
+            .transpose()?;
+        let headers = request_headers(settings)?;
+
+        let builder = || {
+            let builder = Client::builder()
+                .user_agent(user_agent)
+                .timeout(Duration::from_secs(timeout_seconds))
+                .dns_resolver(Arc::new(self.guard.clone()));
+            match &proxy {
+                Some(proxy) => builder.proxy(proxy.clone()),
+                None => builder,
+            }
+        };
+        let build_error =
+            |e: reqwest::Error| AppError::ValidationError(format!("Invalid HTTP settings: {}", e));
+
+        // The headers are only sent by hand-followed requests, which drop them when a
+        // redirect leaves the feed's origin
+        let client = builder()
+            .redirect(self.guard.redirect_policy())
+            .build()
+            .map_err(build_error)?;
+        let feed_client = builder()
+            .redirect(Policy::none())
+            .build()
+            .map_err(build_error)?;
+
+        Ok(FeedFetcher {
+            client,
+            feed_client,
+            feed_headers: headers,
+            ..self.clone()
+        })
+    }
+}
+
+/// Headers carrying the custom headers, cookies and credentials of a feed
+fn request_headers(settings: &FeedHttpSettings) -> Result<HeaderMap, AppError> {
+    let invalid = |what: &str, name: &str| {
+        AppError::ValidationError(format!("Invalid {} '{}' in HTTP settings", what, name))
+    };
+    let secret_value = |value: String, what: &str, name: &str| {
+        let mut value = HeaderValue::from_str(&value).map_err(|_| invalid(what, name))?;
+        value.set_sensitive(true);
+        Ok::<_, AppError>(value)
+    };
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &settings.headers {
+        let header = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| invalid("header name", name))?;
+        headers.insert(header, secret_value(value.clone(), "header", name)?);
+    }
+
+    if !settings.cookies.is_empty() {
+        let cookies = settings
+            .cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name.trim(), value.trim()))
+            .collect::<Vec<_>>()
+            .join("; ");
+        headers.insert(COOKIE, secret_value(cookies, "cookie", "Cookie")?);
+    }
+
+    match &settings.auth {
+        Some(HttpAuth::Basic { username, password }) => {
+            let credentials = BASE64.encode(format!("{}:{}", username, password));
+            let value = secret_value(format!("Basic {}", credentials), "credential", username)?;
+            headers.insert(AUTHORIZATION, value);
+        }
+        Some(HttpAuth::Bearer { token }) => {
+            let value = secret_value(format!("Bearer {}", token.trim()), "token", "bearer")?;
+            headers.insert(AUTHORIZATION, value);
+        }
+        None => {}
+    }
+
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FetcherSettings, SchedulerSettings, WebSubSettings};
+    use crate::feeds::tests::{entries, test_feed, test_settings};
+    use crate::models::SourceType;
+    use sqlx::{PgPool, Row};
+    use std::collections::BTreeMap;
+    use uuid::Uuid;
+    use wiremock::{
+        matchers::{header, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn fetcher() -> FeedFetcher {
+        FeedFetcher::build(
+            FetcherSettings {
+                secret_key: BASE64.encode([7u8; 32]),
+                ..test_settings()
+            },
+            SchedulerSettings::default(),
+            WebSubSettings::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_fetch_feed_applies_http_settings() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/private.xml"))
+            .and(header("authorization", "Basic YWxpY2U6czNjcmV0"))
+            .and(header("cookie", "plan=pro; session=abc"))
+            .and(header("x-api-key", "k3y"))
+            .and(header("user-agent", "Custom Agent"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<rss version="2.0"><channel><title>Private</title>
+                <item><guid>1</guid><title>Members only</title></item></channel></rss>"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let fetcher = fetcher();
+        let settings = FeedHttpSettings {
+            auth: Some(HttpAuth::Basic {
+                username: "alice".to_string(),
+                password: "s3cret".to_string(),
+            }),
+            headers: BTreeMap::from([("X-Api-Key".to_string(), "k3y".to_string())]),
+            cookies: BTreeMap::from([
+                ("session".to_string(), "abc".to_string()),
+                ("plan".to_string(), "pro".to_string()),
+            ]),
+            user_agent: Some("Custom Agent".to_string()),
+            ..Default::default()
+        };
+
+        let mut feed = test_feed(format!("{}/private.xml", server.uri()));
+        feed.http_settings = fetcher.seal_http_settings(&settings).unwrap();
+        let sealed = feed.http_settings.as_deref().unwrap();
+        assert!(!String::from_utf8_lossy(sealed).contains("s3cret"));
+        assert!(!format!("{:?}", settings).contains("s3cret"));
+        assert!(!format!("{:?}", settings).contains("k3y"));
+        assert_eq!(fetcher.open_http_settings(sealed).unwrap(), settings);
+
+        let (_, entries) = entries(fetcher.fetch_feed(&feed).await.unwrap());
+        assert_eq!(entries[0].article.title, "Members only");
+    }
+
+    #[tokio::test]
+    async fn test_feed_headers_stay_on_the_feed_origin() {
+        let feed_server = MockServer::start().await;
+        let other_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/feed.xml"))
+            .and(header("x-api-key", "k3y"))
+            .respond_with(ResponseTemplate::new(302).insert_header(
+                "location",
+                format!("{}/feed.xml", other_server.uri()).as_str(),
+            ))
+            .expect(2)
+            .mount(&feed_server)
+            .await;
+        Mock::given(header_exists("x-api-key"))
+            .respond_with(ResponseTemplate::new(403))
+            .expect(0)
+            .mount(&other_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/feed.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<rss version="2.0"><channel><title>Moved</title>
+                <item><guid>1</guid><title>Elsewhere</title></item></channel></rss>"#,
+            ))
+            .mount(&other_server)
+            .await;
+
+        let fetcher = fetcher();
+        let settings = FeedHttpSettings {
+            headers: BTreeMap::from([("X-Api-Key".to_string(), "k3y".to_string())]),
+            ..Default::default()
+        };
+        let url = format!("{}/feed.xml", feed_server.uri());
+
+        // Subscribing reads the metadata, refreshing fetches the entries
+        let metadata = fetcher
+            .with_http_settings(&settings)
+            .unwrap()
+            .fetch_metadata(SourceType::Rss, &url)
+            .await
+            .unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Moved"));
+
+        let mut feed = test_feed(url);
+        feed.http_settings = fetcher.seal_http_settings(&settings).unwrap();
+        let (_, entries) = entries(fetcher.fetch_feed(&feed).await.unwrap());
+        assert_eq!(entries[0].article.title, "Elsewhere");
+    }
+
+    #[sqlx::test]
+    async fn test_extraction_applies_http_settings(pool: PgPool) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/members/story"))
+            .and(header("authorization", "Bearer t0ken"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                "<html><body><article><p>{}</p></article></body></html>",
+                "Only members get to read this paragraph of the story. ".repeat(5)
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let fetcher = fetcher();
+        let settings = FeedHttpSettings {
+            auth: Some(HttpAuth::Bearer {
+                token: "t0ken".to_string(),
+            }),
+            ..Default::default()
+        };
+        let feed_id: Uuid = sqlx::query(
+            "INSERT INTO feeds (title, url, http_settings) VALUES ('Members', $1, $2) RETURNING id",
+        )
+        .bind(format!("{}/feed.xml", server.uri()))
+        .bind(fetcher.seal_http_settings(&settings).unwrap())
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+        .get("id");
+        let article_id: Uuid = sqlx::query(
+            r#"
+            INSERT INTO articles (title, url, guid, feed_id, content, published_at)
+            VALUES ('Story', $1, 'story', $2, 'Teaser', NOW())
+            RETURNING id
+            "#,
+        )
+        .bind(format!("{}/members/story", server.uri()))
+        .bind(feed_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+        .get("id");
+
+        let article = fetcher
+            .extract_full_content(&pool, article_id)
+            .await
+            .unwrap();
+        assert!(article.content.contains("Only members get to read"));
+        assert_eq!(article.excerpt.as_deref(), Some("Teaser"));
+    }
+
+    #[test]
+    fn test_http_settings_need_a_key() {
+        let settings = FeedHttpSettings {
+            auth: Some(HttpAuth::Bearer {
+                token: "t0ken".to_string(),
+            }),
+            ..Default::default()
+        };
+        let keyless = FeedFetcher::build(
+            FetcherSettings::default(),
+            SchedulerSettings::default(),
+            WebSubSettings::default(),
+        );
+
+        assert!(matches!(
+            keyless.seal_http_settings(&settings),
+            Err(AppError::ValidationError(_))
+        ));
+        assert_eq!(
+            keyless
+                .seal_http_settings(&FeedHttpSettings::default())
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,823 @@
+use super::{content_hash, Author, Enclosure, FeedEntry, FeedFetcher};
+use crate::{
+    error::AppError,
+    models::{DateSource, Feed},
+};
+use sqlx::{PgConnection, PgPool, Row};
+use std::collections::{BTreeMap, HashSet};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Articles written by one ingestion
+#[derive(Debug, Default)]
+pub struct SavedEntries {
+    /// IDs of the articles that were new
+    pub inserted: Vec<Uuid>,
+    /// IDs of known articles whose content changed
+    pub updated: Vec<Uuid>,
+    /// Inserted or updated articles whose full text should be extracted
+    needs_extraction: Vec<Uuid>,
+}
+
+impl FeedFetcher {
+    /// Store parsed entries in one transaction, then extract full text when the
+    /// feed asks for it
+    pub async fn save_entries(
+        &self,
+        pool: &PgPool,
+        feed: &Feed,
+        entries: Vec<FeedEntry>,
+    ) -> Result<SavedEntries, AppError> {
+        self.save_batch(pool, feed, entries, false).await
+    }
+
+    /// `save_entries` for entries of the feed's history, whose new articles are
+    /// stored as archived: like a first import, their made-up dates say nothing
+    pub(super) async fn save_archived_entries(
+        &self,
+        pool: &PgPool,
+        feed: &Feed,
+        entries: Vec<FeedEntry>,
+    ) -> Result<SavedEntries, AppError> {
+        self.save_batch(pool, feed, entries, true).await
+    }
+
+    async fn save_batch(
+        &self,
+        pool: &PgPool,
+        feed: &Feed,
+        entries: Vec<FeedEntry>,
+        archived: bool,
+    ) -> Result<SavedEntries, AppError> {
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let saved = self.write_entries(&mut tx, feed, entries, archived).await?;
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        self.extract_saved(pool, feed, &saved).await;
+        Ok(saved)
+    }
+
+    /// Insert new entries, record edits of known ones and replace their
+    /// enclosures, authors and categories.
+    ///
+    /// Every step handles the whole batch in a single statement, so the number
+    /// of round trips does not grow with the size of the feed. New articles are
+    /// marked `first_import` when the feed had none yet or `archived` is set.
+    pub(super) async fn write_entries(
+        &self,
+        conn: &mut PgConnection,
+        feed: &Feed,
+        entries: Vec<FeedEntry>,
+        archived: bool,
+    ) -> Result<SavedEntries, AppError> {
+        // A feed repeating an entry ID gets the first copy stored
+        let mut seen = HashSet::new();
+        let entries: Vec<FeedEntry> = entries
+            .into_iter()
+            .filter(|entry| seen.insert(entry.article.guid.clone()))
+            .collect();
+        if entries.is_empty() {
+            return Ok(SavedEntries::default());
+        }
+
+        let guids: Vec<&str> = entries.iter().map(|e| e.article.guid.as_str()).collect();
+        let urls: Vec<&str> = entries.iter().map(|e| e.article.url.as_str()).collect();
+
+        // Articles stored before entry IDs were tracked are keyed by URL,
+        // adopt them instead of inserting a duplicate
+        sqlx::query(
+            r#"
+            UPDATE articles a SET guid = e.guid
+            FROM UNNEST($2::text[], $3::text[]) AS e(guid, url)
+            WHERE a.feed_id = $1 AND e.url <> '' AND a.guid = 'legacy:' || e.url
+            AND NOT EXISTS (SELECT 1 FROM articles WHERE feed_id = $1 AND guid = e.guid)
+            "#,
+        )
+        .bind(feed.id)
+        .bind(&guids)
+        .bind(&urls)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        let ids: Vec<Uuid> = entries.iter().map(|e| e.article.id).collect();
+        let titles: Vec<&str> = entries.iter().map(|e| e.article.title.as_str()).collect();
+        let contents: Vec<&str> = entries.iter().map(|e| e.article.content.as_str()).collect();
+        let texts: Vec<Option<&str>> = entries
+            .iter()
+            .map(|e| e.article.content_text.as_deref())
+            .collect();
+        let published: Vec<_> = entries.iter().map(|e| e.article.published_at).collect();
+        let source_updated: Vec<_> = entries
+            .iter()
+            .map(|e| e.article.source_updated_at)
+            .collect();
+        let date_sources: Vec<DateSource> = entries.iter().map(|e| e.article.date_source).collect();
+        let hashes: Vec<String> = entries
+            .iter()
+            .map(|e| content_hash(&e.article.title, &e.article.content))
+            .collect();
+
+        // Insert the entries this feed does not have yet. Entries without a
+        // usable date are listed under the time they are first seen, and the
+        // ones of a feed's first import or history are marked as such.
+        let inserted: Vec<(Uuid, String)> = sqlx::query(
+            r#"
+            INSERT INTO articles
+            (id, title, url, guid, feed_id, content, content_text, published_at,
+             source_updated_at, content_hash, first_seen_at, date_source, first_import)
+            SELECT e.id, e.title, e.url, e.guid, $1, e.content, e.content_text,
+                   CASE WHEN e.date_source IN ('published', 'updated')
+                        THEN e.published_at ELSE NOW() END,
+                   e.source_updated_at, e.content_hash, NOW(), e.date_source,
+                   $12 OR NOT EXISTS (SELECT 1 FROM articles WHERE feed_id = $1)
+            FROM UNNEST($2::uuid[], $3::text[], $4::text[], $5::text[], $6::text[],
+                        $7::text[], $8::timestamptz[], $9::timestamptz[], $10::text[],
+                        $11::text[])
+            AS e(id, title, url, guid, content, content_text, published_at,
+                 source_updated_at, content_hash, date_source)
+            ON CONFLICT (feed_id, guid) DO NOTHING
+            RETURNING id, url
+            "#,
+        )
+        .bind(feed.id)
+        .bind(&ids)
+        .bind(&titles)
+        .bind(&urls)
+        .bind(&guids)
+        .bind(&contents)
+        .bind(&texts)
+        .bind(&published)
+        .bind(&source_updated)
+        .bind(&hashes)
+        .bind(&date_sources)
+        .bind(archived)
+        .map(|row: sqlx::postgres::PgRow| (row.get("id"), row.get("url")))
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to save articles of feed '{}': {}", feed.title, e);
+            AppError::DatabaseError(e.to_string())
+        })?;
+
+        // Known entries get the new version stored when the source changed them.
+        // A copy older than what we have (e.g. from a stale mirror) is ignored.
+        // The stored excerpt belongs to the old version and is dropped.
+        let updated: Vec<(Uuid, String)> = sqlx::query(
+            r#"
+            WITH entries AS (
+                SELECT * FROM UNNEST($2::text[], $3::text[], $4::text[], $5::text[],
+                                     $6::text[], $7::timestamptz[], $8::text[])
+                AS e(guid, title, url, content, content_text, source_updated_at, content_hash)
+            ), previous AS (
+                SELECT a.* FROM articles a
+                JOIN entries e ON e.guid = a.guid
+                WHERE a.feed_id = $1
+                AND a.content_hash IS DISTINCT FROM e.content_hash
+                AND NOT (a.source_updated_at IS NOT NULL AND e.source_updated_at IS NOT NULL
+                         AND e.source_updated_at < a.source_updated_at)
+                FOR UPDATE OF a
+            ), revision AS (
+                INSERT INTO article_revisions
+                (article_id, title, url, content, published_at,
+                 source_updated_at, content_hash)
+                SELECT id, title, url, content, published_at,
+                       source_updated_at, content_hash
+                FROM previous
+            )
+            UPDATE articles a
+            SET title = e.title, url = e.url, content = e.content,
+                content_text = e.content_text, excerpt = NULL,
+                source_updated_at = e.source_updated_at, content_hash = e.content_hash,
+                updated_at = NOW()
+            FROM previous, entries e
+            WHERE a.id = previous.id AND e.guid = previous.guid
+            RETURNING a.id, a.url
+            "#,
+        )
+        .bind(feed.id)
+        .bind(&guids)
+        .bind(&titles)
+        .bind(&urls)
+        .bind(&contents)
+        .bind(&texts)
+        .bind(&source_updated)
+        .bind(&hashes)
+        .map(|row: sqlx::postgres::PgRow| (row.get("id"), row.get("url")))
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to update articles of feed '{}': {}", feed.title, e);
+            AppError::DatabaseError(e.to_string())
+        })?;
+
+        self.store_enclosures(conn, feed, &entries).await?;
+        self.store_authors(conn, feed, &entries).await?;
+        self.store_categories(conn, feed, &entries).await?;
+
+        let needs_extraction = if feed.fetch_full_content {
+            inserted
+                .iter()
+                .chain(&updated)
+                .filter(|(_, url)| !url.is_empty())
+                .map(|(id, _)| *id)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        info!(
+            "Saved {} new and {} updated articles from feed: {}",
+            inserted.len(),
+            updated.len(),
+            feed.title
+        );
+        Ok(SavedEntries {
+            inserted: inserted.into_iter().map(|(id, _)| id).collect(),
+            updated: updated.into_iter().map(|(id, _)| id).collect(),
+            needs_extraction,
+        })
+    }
+
+    /// Extract the full text of saved articles, once their transaction is committed
+    pub(super) async fn extract_saved(&self, pool: &PgPool, feed: &Feed, saved: &SavedEntries) {
+        if saved.needs_extraction.is_empty() {
+            return;
+        }
+        let fetcher = match self.for_feed(feed) {
+            Ok(fetcher) => fetcher,
+            Err(e) => {
+                warn!(
+                    "Failed to extract full content for feed {}: {}",
+                    feed.title, e
+                );
+                return;
+            }
+        };
+
+        // A page that cannot be extracted keeps the feed content, it is not a refresh failure
+        for article_id in &saved.needs_extraction {
+            if let Err(e) = fetcher.extract_article_page(pool, *article_id).await {
+                warn!(
+                    "Failed to extract full content for article {}: {}",
+                    article_id, e
+                );
+            }
+        }
+    }
+
+    /// Replace the stored enclosures of the entries with the ones currently in the feed
+    async fn store_enclosures(
+        &self,
+        conn: &mut PgConnection,
+        feed: &Feed,
+        entries: &[FeedEntry],
+    ) -> Result<(), AppError> {
+        let mut seen = HashSet::new();
+        let enclosures: Vec<(&str, &Enclosure)> = entries
+            .iter()
+            .flat_map(|e| {
+                e.enclosures
+                    .iter()
+                    .map(|enclosure| (e.article.guid.as_str(), enclosure))
+            })
+            .filter(|(guid, enclosure)| seen.insert((*guid, enclosure.url.as_str())))
+            .collect();
+        let guids: Vec<&str> = entries.iter().map(|e| e.article.guid.as_str()).collect();
+        let enclosure_guids: Vec<&str> = enclosures.iter().map(|(guid, _)| *guid).collect();
+        let urls: Vec<&str> = enclosures.iter().map(|(_, e)| e.url.as_str()).collect();
+
+        sqlx::query(
+            r#"
+            DELETE FROM article_enclosures ae
+            USING articles a
+            WHERE ae.article_id = a.id AND a.feed_id = $1 AND a.guid = ANY($2)
+            AND NOT EXISTS (
+                SELECT 1 FROM UNNEST($3::text[], $4::text[]) AS e(guid, url)
+                WHERE e.guid = a.guid AND e.url = ae.url
+            )
+            "#,
+        )
+        .bind(feed.id)
+        .bind(&guids)
+        .bind(&enclosure_guids)
+        .bind(&urls)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        if enclosures.is_empty() {
+            return Ok(());
+        }
+
+        let mime_types: Vec<Option<&str>> = enclosures
+            .iter()
+            .map(|(_, e)| e.mime_type.as_deref())
+            .collect();
+        let lengths: Vec<Option<i64>> = enclosures.iter().map(|(_, e)| e.length).collect();
+        let durations: Vec<Option<i32>> =
+            enclosures.iter().map(|(_, e)| e.duration_seconds).collect();
+        let thumbnails: Vec<Option<&str>> = enclosures
+            .iter()
+            .map(|(_, e)| e.thumbnail_url.as_deref())
+            .collect();
+
+        sqlx::query(
+            r#"
+            INSERT INTO article_enclosures
+            (article_id, url, mime_type, length, duration_seconds, thumbnail_url)
+            SELECT a.id, e.url, e.mime_type, e.length, e.duration_seconds, e.thumbnail_url
+            FROM UNNEST($2::text[], $3::text[], $4::text[], $5::bigint[], $6::int[], $7::text[])
+            AS e(guid, url, mime_type, length, duration_seconds, thumbnail_url)
+            JOIN articles a ON a.feed_id = $1 AND a.guid = e.guid
+            ON CONFLICT (article_id, url) DO UPDATE
+            SET mime_type = EXCLUDED.mime_type, length = EXCLUDED.length,
+                duration_seconds = EXCLUDED.duration_seconds,
+                thumbnail_url = EXCLUDED.thumbnail_url
+            "#,
+        )
+        .bind(feed.id)
+        .bind(&enclosure_guids)
+        .bind(&urls)
+        .bind(&mime_types)
+        .bind(&lengths)
+        .bind(&durations)
+        .bind(&thumbnails)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to save enclosures of feed '{}': {}", feed.title, e);
+            AppError::DatabaseError(e.to_string())
+        })?;
+
+        Ok(())
+    }
+
+    /// Link the entries to their authors, creating authors seen for the first time
+    async fn store_authors(
+        &self,
+        conn: &mut PgConnection,
+        feed: &Feed,
+        entries: &[FeedEntry],
+    ) -> Result<(), AppError> {
+        let links: Vec<(&str, &Author)> = entries
+            .iter()
+            .flat_map(|e| {
+                e.authors
+                    .iter()
+                    .map(|author| (e.article.guid.as_str(), author))
+            })
+            .collect();
+        let guids: Vec<&str> = entries.iter().map(|e| e.article.guid.as_str()).collect();
+        let link_guids: Vec<&str> = links.iter().map(|(guid, _)| *guid).collect();
+        let link_names: Vec<&str> = links.iter().map(|(_, a)| a.name.as_str()).collect();
+
+        sqlx::query(
+            r#"
+            DELETE FROM article_authors aa
+            USING articles a
+            WHERE aa.article_id = a.id AND a.feed_id = $1 AND a.guid = ANY($2)
+            AND NOT EXISTS (
+                SELECT 1 FROM UNNEST($3::text[], $4::text[]) AS e(guid, name)
+                JOIN authors au ON au.name = e.name
+                WHERE e.guid = a.guid AND au.id = aa.author_id
+            )
+            "#,
+        )
+        .bind(feed.id)
+        .bind(&guids)
+        .bind(&link_guids)
+        .bind(&link_names)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        if links.is_empty() {
+            return Ok(());
+        }
+
+        // One row per name, in name order so concurrent refreshes lock authors alike
+        let mut authors: BTreeMap<&str, (Option<&str>, Option<&str>)> = BTreeMap::new();
+        for (_, author) in &links {
+            let (email, uri) = authors.entry(author.name.as_str()).or_default();
+            *email = email.or(author.email.as_deref());
+            *uri = uri.or(author.uri.as_deref());
+        }
+        let names: Vec<&str> = authors.keys().copied().collect();
+        let emails: Vec<Option<&str>> = authors.values().map(|(email, _)| *email).collect();
+        let uris: Vec<Option<&str>> = authors.values().map(|(_, uri)| *uri).collect();
+
+        sqlx::query(
+            r#"
+            INSERT INTO authors (name, email, uri)
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[])
+            ON CONFLICT (name) DO UPDATE
+            SET email = COALESCE(EXCLUDED.email, authors.email),
+                uri = COALESCE(EXCLUDED.uri, authors.uri)
+            "#,
+        )
+        .bind(&names)
+        .bind(&emails)
+        .bind(&uris)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to save authors of feed '{}': {}", feed.title, e);
+            AppError::DatabaseError(e.to_string())
+        })?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO article_authors (article_id, author_id)
+            SELECT a.id, au.id
+            FROM UNNEST($2::text[], $3::text[]) AS e(guid, name)
+            JOIN articles a ON a.feed_id = $1 AND a.guid = e.guid
+            JOIN authors au ON au.name = e.name
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(feed.id)
+        .bind(&link_guids)
+        .bind(&link_names)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Link the entries to their categories, creating categories seen for the first time
+    async fn store_categories(
+        &self,
+        conn: &mut PgConnection,
+        feed: &Feed,
+        entries: &[FeedEntry],
+    ) -> Result<(), AppError> {
+        let links: Vec<(&str, &str)> = entries
+            .iter()
+            .flat_map(|e| {
+                e.categories
+                    .iter()
+                    .map(|category| (e.article.guid.as_str(), category.as_str()))
+            })
+            .collect();
+        let guids: Vec<&str> = entries.iter().map(|e| e.article.guid.as_str()).collect();
+        let link_guids: Vec<&str> = links.iter().map(|(guid, _)| *guid).collect();
+        let link_names: Vec<&str> = links.iter().map(|(_, name)| *name).collect();
+
+        sqlx::query(
+            r#"
+            DELETE FROM article_categories ac
+            USING articles a
+            WHERE ac.article_id = a.id AND a.feed_id = $1 AND a.guid = ANY($2)
+            AND NOT EXISTS (
+                SELECT 1 FROM UNNEST($3::text[], $4::text[]) AS e(guid, name)
+                JOIN categories c ON c.name = e.name
+                WHERE e.guid = a.guid AND c.id = ac.category_id
+            )
+            "#,
+        )
+        .bind(feed.id)
+        .bind(&guids)
+        .bind(&link_guids)
+        .bind(&link_names)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        if links.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO categories (name)
+            SELECT DISTINCT name FROM UNNEST($1::text[]) AS e(name) ORDER BY name
+            ON CONFLICT (name) DO NOTHING
+            "#,
+        )
+        .bind(&link_names)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("Failed to save categories of feed '{}': {}", feed.title, e);
+            AppError::DatabaseError(e.to_string())
+        })?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO article_categories (article_id, category_id)
+            SELECT a.id, c.id
+            FROM UNNEST($2::text[], $3::text[]) AS e(guid, name)
+            JOIN articles a ON a.feed_id = $1 AND a.guid = e.guid
+            JOIN categories c ON c.name = e.name
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(feed.id)
+        .bind(&link_guids)
+        .bind(&link_names)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        feeds::{feed_parser, tests::test_fetcher},
+        routes::articles::has_known_date,
+    };
+
+    async fn insert_feed(fetcher: &FeedFetcher, pool: &PgPool) -> Feed {
+        let feed_id: Uuid = sqlx::query(
+            "INSERT INTO feeds (title, url) VALUES ('Test', 'https://example.com/feed.xml') RETURNING id",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap()
+        .get("id");
+        fetcher.load_feed(pool, feed_id).await.unwrap()
+    }
+
+    fn parse_entries(fetcher: &FeedFetcher, feed: &Feed, items: &str) -> Vec<FeedEntry> {
+        let xml = format!(
+            r#"<?xml version="1.0"?><rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/"><channel><title>Test</title>{}</channel></rss>"#,
+            items
+        );
+        let parsed = feed_parser(&feed.url).parse(xml.as_bytes()).unwrap();
+        fetcher.rss_to_articles(feed, parsed).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_only_the_first_import_is_marked(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let feed = insert_feed(&fetcher, &pool).await;
+
+        let first = parse_entries(
+            &fetcher,
+            &feed,
+            r#"
+            <item><guid>a</guid><title>A</title></item>
+            <item><guid>c</guid><title>C</title><pubDate>Tue, 10 Jun 2003 04:00:00 GMT</pubDate></item>
+            <item><guid>f</guid><title>F</title><pubDate>Fri, 01 Jan 2100 00:00:00 GMT</pubDate></item>
+            "#,
+        );
+        fetcher.save_entries(&pool, &feed, first).await.unwrap();
+        let later = parse_entries(
+            &fetcher,
+            &feed,
+            "<item><guid>a</guid><title>A</title></item><item><guid>b</guid><title>B</title></item>",
+        );
+        fetcher.save_entries(&pool, &feed, later).await.unwrap();
+
+        let marked: Vec<(String, DateSource, bool)> = sqlx::query(
+            "SELECT guid, date_source, first_import FROM articles WHERE feed_id = $1 ORDER BY guid",
+        )
+        .bind(feed.id)
+        .map(|row: sqlx::postgres::PgRow| {
+            (
+                row.get("guid"),
+                row.get("date_source"),
+                row.get("first_import"),
+            )
+        })
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            marked,
+            vec![
+                ("a".to_string(), DateSource::Missing, true),
+                ("b".to_string(), DateSource::Missing, false),
+                ("c".to_string(), DateSource::Published, true),
+                ("f".to_string(), DateSource::ClampedFromFuture, true),
+            ]
+        );
+
+        // Only the first import's made-up dates are left out of date windows
+        let dated: Vec<String> = sqlx::query(&format!(
+            "SELECT guid FROM articles WHERE feed_id = $1 AND {} ORDER BY guid",
+            has_known_date("articles")
+        ))
+        .bind(feed.id)
+        .map(|row: sqlx::postgres::PgRow| row.get("guid"))
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(dated, vec!["b".to_string(), "c".to_string()]);
+    }
+
+    async fn article_ids(pool: &PgPool, feed: &Feed) -> Vec<(String, Uuid)> {
+        sqlx::query("SELECT guid, id FROM articles WHERE feed_id = $1 ORDER BY guid")
+            .bind(feed.id)
+            .map(|row: sqlx::postgres::PgRow| (row.get("guid"), row.get("id")))
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_reingesting_updates_instead_of_duplicating(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let feed = insert_feed(&fetcher, &pool).await;
+
+        let entries = parse_entries(
+            &fetcher,
+            &feed,
+            "<item><guid>a</guid><title>A</title></item><item><guid>b</guid><title>B</title></item>",
+        );
+        let saved = fetcher.save_entries(&pool, &feed, entries).await.unwrap();
+        assert_eq!(saved.inserted.len(), 2);
+        let stored = article_ids(&pool, &feed).await;
+
+        let entries = parse_entries(
+            &fetcher,
+            &feed,
+            "<item><guid>a</guid><title>A, edited</title></item><item><guid>b</guid><title>B</title></item>",
+        );
+        let saved = fetcher.save_entries(&pool, &feed, entries).await.unwrap();
+        assert!(saved.inserted.is_empty());
+        assert_eq!(saved.updated, vec![stored[0].1]);
+
+        assert_eq!(article_ids(&pool, &feed).await, stored);
+        let title: String = sqlx::query("SELECT title FROM articles WHERE id = $1")
+            .bind(stored[0].1)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .get("title");
+        assert_eq!(title, "A, edited");
+    }
+
+    #[sqlx::test]
+    async fn test_entry_details_are_attached_to_their_articles(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let feed = insert_feed(&fetcher, &pool).await;
+
+        let entries = parse_entries(
+            &fetcher,
+            &feed,
+            r#"
+            <item><guid>a</guid><title>A</title><dc:creator>Alice</dc:creator><category>Rust</category>
+            <enclosure url="https://example.com/a.mp3" type="audio/mpeg" length="1"/></item>
+            <item><guid>b</guid><title>B</title><dc:creator>Bob</dc:creator><category>Go</category>
+            <enclosure url="https://example.com/b.mp3" type="audio/mpeg" length="2"/></item>
+            "#,
+        );
+        fetcher.save_entries(&pool, &feed, entries).await.unwrap();
+
+        let details: Vec<(String, String, String, String)> = sqlx::query(
+            r#"
+            SELECT a.guid, ae.url, au.name AS author, c.name AS category
+            FROM articles a
+            JOIN article_enclosures ae ON ae.article_id = a.id
+            JOIN article_authors aa ON aa.article_id = a.id
+            JOIN authors au ON au.id = aa.author_id
+            JOIN article_categories ac ON ac.article_id = a.id
+            JOIN categories c ON c.id = ac.category_id
+            WHERE a.feed_id = $1
+            ORDER BY a.guid
+            "#,
+        )
+        .bind(feed.id)
+        .map(|row: sqlx::postgres::PgRow| {
+            (
+                row.get("guid"),
+                row.get("url"),
+                row.get("author"),
+                row.get("category"),
+            )
+        })
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            details,
+            vec![
+                (
+                    "a".to_string(),
+                    "https://example.com/a.mp3".to_string(),
+                    "Alice".to_string(),
+                    "Rust".to_string()
+                ),
+                (
+                    "b".to_string(),
+                    "https://example.com/b.mp3".to_string(),
+                    "Bob".to_string(),
+                    "Go".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_failure_mid_batch_rolls_back_the_whole_batch(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let feed = insert_feed(&fetcher, &pool).await;
+
+        let entries = parse_entries(
+            &fetcher,
+            &feed,
+            "<item><guid>a</guid><title>A</title></item>",
+        );
+        fetcher.save_entries(&pool, &feed, entries).await.unwrap();
+        let stored = article_ids(&pool, &feed).await;
+
+        // The articles are written before Postgres rejects the enclosure's NUL byte
+        let mut entries = parse_entries(
+            &fetcher,
+            &feed,
+            "<item><guid>a</guid><title>A, edited</title></item><item><guid>b</guid><title>B</title></item>",
+        );
+        entries[1].enclosures.push(Enclosure {
+            url: "https://example.com/\0.mp3".to_string(),
+            mime_type: None,
+            length: None,
+            duration_seconds: None,
+            thumbnail_url: None,
+        });
+        assert!(fetcher.save_entries(&pool, &feed, entries).await.is_err());
+
+        assert_eq!(article_ids(&pool, &feed).await, stored);
+        let title: String = sqlx::query("SELECT title FROM articles WHERE id = $1")
+            .bind(stored[0].1)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .get("title");
+        assert_eq!(title, "A");
+        let revisions: i64 = sqlx::query("SELECT COUNT(*) AS count FROM article_revisions")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .get("count");
+        assert_eq!(revisions, 0);
+    }
+
+    async fn revisions(pool: &PgPool, article_id: Uuid) -> Vec<(String, String)> {
+        sqlx::query(
+            "SELECT title, content FROM article_revisions WHERE article_id = $1 ORDER BY created_at",
+        )
+        .bind(article_id)
+        .map(|row: sqlx::postgres::PgRow| (row.get("title"), row.get("content")))
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_edits_record_the_previous_version(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let feed = insert_feed(&fetcher, &pool).await;
+        let original =
+            "<item><guid>a</guid><title>A</title><description>First</description></item>";
+
+        let entries = parse_entries(&fetcher, &feed, original);
+        fetcher.save_entries(&pool, &feed, entries).await.unwrap();
+        let article_id = article_ids(&pool, &feed).await[0].1;
+        let (title, content): (String, String) =
+            sqlx::query("SELECT title, content FROM articles WHERE id = $1")
+                .bind(article_id)
+                .map(|row: sqlx::postgres::PgRow| (row.get("title"), row.get("content")))
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+
+        // An unchanged re-fetch is not an edit
+        let entries = parse_entries(&fetcher, &feed, original);
+        fetcher.save_entries(&pool, &feed, entries).await.unwrap();
+        assert!(revisions(&pool, article_id).await.is_empty());
+
+        let entries = parse_entries(
+            &fetcher,
+            &feed,
+            "<item><guid>a</guid><title>A, edited</title><description>First</description></item>",
+        );
+        fetcher.save_entries(&pool, &feed, entries).await.unwrap();
+        assert_eq!(
+            revisions(&pool, article_id).await,
+            vec![(title.clone(), content.clone())]
+        );
+
+        let entries = parse_entries(
+            &fetcher,
+            &feed,
+            "<item><guid>a</guid><title>A, edited</title><description>Second</description></item>",
+        );
+        fetcher.save_entries(&pool, &feed, entries).await.unwrap();
+        assert_eq!(
+            revisions(&pool, article_id).await,
+            vec![(title, content.clone()), ("A, edited".to_string(), content)]
+        );
+    }
+}
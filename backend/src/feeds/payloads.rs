@@ -0,0 +1,297 @@
+use super::{body, feed_parser, FeedFetcher, SavedEntries};
+use crate::error::AppError;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use reqwest::{
+    header::{HeaderMap, CONTENT_TYPE, SET_COOKIE},
+    Response,
+};
+use sqlx::{types::Json as SqlJson, PgConnection, PgPool, Row};
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+};
+use tracing::info;
+use uuid::Uuid;
+
+/// A feed document as it was fetched, before any decoding
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawPayload {
+    pub url: String,
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl RawPayload {
+    /// `Content-Type` the document was served with
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(CONTENT_TYPE.as_str()).map(String::as_str)
+    }
+}
+
+impl FeedFetcher {
+    /// `read_feed`, also returning the document as fetched when payloads are kept
+    pub(super) async fn read_feed_payload(
+        &self,
+        response: Response,
+    ) -> Result<(Vec<u8>, Option<Box<RawPayload>>), AppError> {
+        let content_type = body::content_type(&response);
+        let url = response.url().to_string();
+        let status = response.status().as_u16();
+        let headers = stored_headers(response.headers());
+
+        let body = body::read_body(response, self.settings.max_body_bytes).await?;
+        let xml = body::decode_feed(&body, content_type.as_deref())?.into_owned();
+        let payload = RawPayload {
+            url,
+            status,
+            headers,
+            body,
+        };
+        let payload = (self.settings.raw_payloads_per_feed > 0).then_some(Box::new(payload));
+        Ok((xml, payload))
+    }
+
+    /// Store a fetched document gzipped, dropping the feed's documents past
+    /// `fetcher.raw_payloads_per_feed`
+    pub(super) async fn store_payload(
+        &self,
+        conn: &mut PgConnection,
+        feed_id: Uuid,
+        payload: &RawPayload,
+    ) -> Result<(), AppError> {
+        let keep = self.settings.raw_payloads_per_feed;
+        if keep == 0 {
+            return Ok(());
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder
+            .write_all(&payload.body)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| AppError::InternalServerError(format!("Failed to compress: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO feed_payloads (feed_id, url, status, headers, body, size_bytes)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(feed_id)
+        .bind(&payload.url)
+        .bind(payload.status as i32)
+        .bind(SqlJson(&payload.headers))
+        .bind(compressed)
+        .bind(payload.body.len() as i32)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM feed_payloads
+            WHERE feed_id = $1 AND id NOT IN (
+                SELECT id FROM feed_payloads
+                WHERE feed_id = $1
+                ORDER BY fetched_at DESC
+                LIMIT $2
+            )
+            "#,
+        )
+        .bind(feed_id)
+        .bind(keep as i64)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// A stored document of a feed, decompressed
+    pub async fn load_payload(
+        &self,
+        pool: &PgPool,
+        feed_id: Uuid,
+        payload_id: Uuid,
+    ) -> Result<RawPayload, AppError> {
+        let row = sqlx::query(
+            "SELECT url, status, headers, body FROM feed_payloads WHERE id = $1 AND feed_id = $2",
+        )
+        .bind(payload_id)
+        .bind(feed_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "Payload {} of feed {} not found",
+                payload_id, feed_id
+            ))
+        })?;
+
+        let compressed: Vec<u8> = row.get("body");
+        let mut body = Vec::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut body)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to decompress: {}", e)))?;
+        let SqlJson(headers) = row.get("headers");
+
+        Ok(RawPayload {
+            url: row.get("url"),
+            status: row.get::<i32, _>("status") as u16,
+            headers,
+            body,
+        })
+    }
+
+    /// Run ingestion again on a stored document, as if it had just been fetched.
+    ///
+    /// The feed's cache validators and schedule are left alone.
+    pub async fn reprocess_payload(
+        &self,
+        pool: &PgPool,
+        feed_id: Uuid,
+        payload_id: Uuid,
+    ) -> Result<SavedEntries, AppError> {
+        let feed = self.load_feed(pool, feed_id).await?;
+        let payload = self.load_payload(pool, feed_id, payload_id).await?;
+
+        let xml = body::decode_feed(&payload.body, payload.content_type())?;
+        let parsed_feed = feed_parser(&payload.url)
+            .parse(xml.as_ref())
+            .map_err(|e| AppError::FeedParsingError(format!("Failed to parse XML: {}", e)))?;
+        let entries = self.rss_to_articles(&feed, parsed_feed)?;
+
+        info!(
+            "Reprocessing {} articles of feed {} from payload {}",
+            entries.len(),
+            feed.title,
+            payload_id
+        );
+        self.save_entries(pool, &feed, entries).await
+    }
+}
+
+/// Response headers worth keeping, cookies are left out
+fn stored_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    let mut stored = BTreeMap::new();
+    for (name, value) in headers {
+        if name == SET_COOKIE {
+            continue;
+        }
+        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+        stored
+            .entry(name.as_str().to_string())
+            .and_modify(|joined: &mut String| {
+                joined.push_str(", ");
+                joined.push_str(&value);
+            })
+            .or_insert(value);
+    }
+    stored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{FetcherSettings, SchedulerSettings, WebSubSettings},
+        feeds::tests::test_settings,
+    };
+    use reqwest::header::{CACHE_CONTROL, ETAG};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_stored_headers_join_repeats_and_drop_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, "\"v1\"".parse().unwrap());
+        headers.append(CACHE_CONTROL, "public".parse().unwrap());
+        headers.append(CACHE_CONTROL, "max-age=60".parse().unwrap());
+        headers.insert(SET_COOKIE, "session=abc".parse().unwrap());
+
+        assert_eq!(
+            stored_headers(&headers),
+            BTreeMap::from([
+                (
+                    "cache-control".to_string(),
+                    "public, max-age=60".to_string()
+                ),
+                ("etag".to_string(), "\"v1\"".to_string()),
+            ])
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_payloads_are_kept_up_to_the_limit_and_reprocessed(pool: PgPool) {
+        let fetcher = FeedFetcher::build(
+            FetcherSettings {
+                raw_payloads_per_feed: 2,
+                ..test_settings()
+            },
+            SchedulerSettings::default(),
+            WebSubSettings::default(),
+        );
+        let server = MockServer::start().await;
+        let feed_id: Uuid =
+            sqlx::query("INSERT INTO feeds (title, url) VALUES ('Test', $1) RETURNING id")
+                .bind(format!("{}/feed.xml", server.uri()))
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+                .get("id");
+
+        let document = |guid: &str| {
+            format!(
+                r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Test</title><item><guid>{0}</guid><title>{0}</title></item></channel></rss>"#,
+                guid
+            )
+        };
+        for guid in ["v1", "v2", "v3"] {
+            server.reset().await;
+            Mock::given(method("GET"))
+                .and(path("/feed.xml"))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_raw(document(guid), "application/rss+xml"),
+                )
+                .mount(&server)
+                .await;
+            fetcher.refresh_feed(&pool, feed_id).await.unwrap();
+        }
+
+        let kept: Vec<Uuid> =
+            sqlx::query("SELECT id FROM feed_payloads WHERE feed_id = $1 ORDER BY fetched_at")
+                .bind(feed_id)
+                .map(|row: sqlx::postgres::PgRow| row.get("id"))
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(kept.len(), 2);
+
+        // The oldest document kept is the second one, decompressed as fetched
+        let payload = fetcher.load_payload(&pool, feed_id, kept[0]).await.unwrap();
+        assert_eq!(payload.status, 200);
+        assert_eq!(payload.content_type(), Some("application/rss+xml"));
+        assert_eq!(payload.body, document("v2").into_bytes());
+
+        sqlx::query("DELETE FROM articles WHERE feed_id = $1")
+            .bind(feed_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let saved = fetcher
+            .reprocess_payload(&pool, feed_id, kept[0])
+            .await
+            .unwrap();
+        assert_eq!(saved.inserted.len(), 1);
+        let guid: String = sqlx::query("SELECT guid FROM articles WHERE feed_id = $1")
+            .bind(feed_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .get("guid");
+        assert_eq!(guid, "v2");
+    }
+}
@@ -0,0 +1,353 @@
+use super::{
+    body, entry_date, fulltext::escape_html, guard, guard::AddressGuard, Author, Enclosure,
+    FeedEntry, FeedFetcher, FeedMetadata, FetchOutcome, MAX_REDIRECTS,
+};
+use crate::{
+    content,
+    error::AppError,
+    models::{Article, Feed, SourceType},
+    scheduler::CacheHints,
+};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use reqwest::{
+    header::{HeaderMap, ACCEPT, ETAG, IF_NONE_MATCH, LOCATION},
+    Client, Response, StatusCode, Url,
+};
+use serde::de::DeserializeOwned;
+use tracing::info;
+use uuid::Uuid;
+
+mod github;
+mod hacker_news;
+mod mastodon;
+mod newsletter;
+mod reddit;
+mod scraped;
+mod sitemap;
+
+pub use github::GithubReleases;
+pub use hacker_news::HackerNews;
+pub use mastodon::Mastodon;
+pub use newsletter::Newsletter;
+pub use reddit::Reddit;
+pub use scraped::ScrapedPage;
+pub use sitemap::Sitemap;
+
+/// Somewhere feed entries can be read from.
+///
+/// `FeedFetcher` dispatches on a feed's `source_type`, so supporting a new site
+/// means implementing this trait and adding a `SourceType` variant.
+pub trait FeedSource: Send + Sync {
+    /// Fetch the entries currently published by the source
+    fn fetch<'a>(&'a self, feed: &'a Feed) -> BoxFuture<'a, Result<FetchOutcome, AppError>>;
+
+    /// Describe the source behind `url`, failing if there is nothing to follow
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FeedMetadata, AppError>>;
+}
+
+/// RSS, Atom and JSON Feed documents fetched over HTTP
+pub struct RssSource<'a> {
+    fetcher: &'a FeedFetcher,
+}
+
+impl FeedSource for RssSource<'_> {
+    fn fetch<'a>(&'a self, feed: &'a Feed) -> BoxFuture<'a, Result<FetchOutcome, AppError>> {
+        self.fetcher.fetch_rss(feed).boxed()
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FeedMetadata, AppError>> {
+        self.fetcher.fetch_rss_metadata(url).boxed()
+    }
+}
+
+impl FeedFetcher {
+    /// The source implementation for a feed type
+    pub fn source(&self, source_type: SourceType) -> Box<dyn FeedSource + '_> {
+        match source_type {
+            SourceType::Rss => Box::new(RssSource { fetcher: self }),
+            SourceType::GithubReleases => Box::new(GithubReleases::new(self.source_client())),
+            SourceType::Reddit => Box::new(Reddit::new(self.source_client())),
+            SourceType::Mastodon => Box::new(Mastodon::new(self.source_client())),
+            SourceType::HackerNews => Box::new(HackerNews::new(self.source_client())),
+            SourceType::Scraped => Box::new(ScrapedPage::new(self.source_client())),
+            SourceType::Newsletter => Box::new(Newsletter),
+            SourceType::Sitemap => Box::new(Sitemap::new(self.source_client())),
+        }
+    }
+
+    pub(super) fn source_client(&self) -> SourceClient {
+        SourceClient {
+            client: self.feed_client.clone(),
+            feed_headers: self.feed_headers.clone(),
+            max_body_bytes: self.settings.max_body_bytes,
+            guard: self.guard.clone(),
+        }
+    }
+}
+
+/// HTTP client of the sources, reading responses up to the fetcher's size limit
+#[derive(Clone)]
+pub struct SourceClient {
+    /// Does not follow redirects, `get_following_redirects` does
+    client: Client,
+    /// Credentials and headers of the feed, sent until a redirect leaves its origin
+    feed_headers: HeaderMap,
+    max_body_bytes: usize,
+    guard: AddressGuard,
+}
+
+impl SourceClient {
+    /// GET a URL with the feed's headers, following up to `MAX_REDIRECTS` redirects.
+    ///
+    /// Every hop is checked against the address guard. Returns the final response,
+    /// its URL and whether every redirect on the way was permanent (`301`/`308`).
+    pub(super) async fn get_following_redirects(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+    ) -> Result<(Response, String, bool), AppError> {
+        let mut headers = {
+            let mut all = self.feed_headers.clone();
+            all.extend(headers);
+            all
+        };
+        let origin = Url::parse(url).ok().map(|url| url.origin());
+        let mut url = url.to_string();
+        let mut permanent = true;
+
+        for _ in 0..=MAX_REDIRECTS {
+            self.guard.check_url(&url)?;
+            let response = self
+                .client
+                .get(&url)
+                .headers(headers.clone())
+                .send()
+                .await
+                .map_err(|e| guard::request_error(&format!("Failed to fetch {}", url), e))?;
+
+            let status = response.status();
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|location| response.url().join(location).ok());
+
+            match location {
+                Some(location) if status.is_redirection() => {
+                    permanent &= matches!(
+                        status,
+                        StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+                    );
+                    // The feed's credentials are not for other sites
+                    if Some(location.origin()) != origin {
+                        for name in self.feed_headers.keys() {
+                            headers.remove(name);
+                        }
+                    }
+                    url = location.to_string();
+                }
+                _ => return Ok((response, url, permanent)),
+            }
+        }
+
+        Err(AppError::FeedParsingError(format!(
+            "Failed to fetch {}: more than {} redirects",
+            url, MAX_REDIRECTS
+        )))
+    }
+}
+
+#[cfg(test)]
+impl Default for SourceClient {
+    /// Allowed to reach the local mock servers of tests
+    fn default() -> Self {
+        let settings = crate::config::FetcherSettings {
+            allowed_hosts: vec!["127.0.0.1".to_string()],
+            ..Default::default()
+        };
+        Self {
+            client: Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap(),
+            feed_headers: HeaderMap::new(),
+            max_body_bytes: settings.max_body_bytes,
+            guard: AddressGuard::new(&settings),
+        }
+    }
+}
+
+/// An item read from a site's API, normalized into a feed entry like RSS items
+#[derive(Debug, Default)]
+struct SourceItem {
+    guid: String,
+    title: String,
+    url: String,
+    /// HTML body, sanitized when converted
+    html: String,
+    published_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    authors: Vec<Author>,
+    categories: Vec<String>,
+    enclosures: Vec<Enclosure>,
+}
+
+impl SourceItem {
+    fn into_entry(self, feed: &Feed) -> FeedEntry {
+        let base_url = if self.url.is_empty() {
+            &feed.url
+        } else {
+            &self.url
+        };
+        let (content, content_text) = content::normalize(&self.html, Some(base_url));
+        let (published_at, date_source) = entry_date(self.published_at, self.updated_at);
+
+        let article = Article {
+            id: Uuid::new_v4(),
+            title: self.title,
+            url: self.url,
+            guid: self.guid,
+            feed_id: feed.id,
+            content,
+            content_text: Some(content_text),
+            excerpt: None,
+            published_at,
+            source_updated_at: self.updated_at,
+            first_seen_at: Utc::now(),
+            date_source,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        FeedEntry {
+            article,
+            enclosures: self.enclosures,
+            authors: self.authors,
+            categories: self.categories,
+        }
+    }
+}
+
+/// Turn the items of one API response into a fetch outcome
+fn fetched(feed: &Feed, items: Vec<SourceItem>, etag: Option<String>) -> FetchOutcome {
+    let articles: Vec<FeedEntry> = items
+        .into_iter()
+        .map(|item| item.into_entry(feed))
+        .collect();
+    info!("Fetched {} articles from {}", articles.len(), feed.title);
+
+    FetchOutcome::Fetched {
+        articles,
+        etag,
+        last_modified: None,
+        hints: CacheHints::default(),
+        moved_to: None,
+        payload: None,
+    }
+}
+
+/// Media type requested from JSON APIs that have no more specific one
+const JSON: &str = "application/json";
+
+/// GET a source's page or API endpoint.
+///
+/// The feed's stored ETag is sent along, `None` means the server answered
+/// `304 Not Modified`.
+async fn get(
+    client: &SourceClient,
+    url: &str,
+    accept: &str,
+    etag: Option<&str>,
+) -> Result<Option<Response>, AppError> {
+    let mut headers = HeaderMap::new();
+    if let Ok(accept) = accept.parse() {
+        headers.insert(ACCEPT, accept);
+    }
+    if let Some(etag) = etag.and_then(|v| v.parse().ok()) {
+        headers.insert(IF_NONE_MATCH, etag);
+    }
+
+    let (response, _, _) = client.get_following_redirects(url, headers).await?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+
+    if !response.status().is_success() {
+        return Err(AppError::FeedParsingError(format!(
+            "Failed to fetch {}. Status: {}",
+            url,
+            response.status()
+        )));
+    }
+
+    Ok(Some(response))
+}
+
+/// GET a JSON API endpoint, along with the response's ETag
+async fn get_json<T: DeserializeOwned>(
+    client: &SourceClient,
+    url: &str,
+    accept: &str,
+    etag: Option<&str>,
+) -> Result<Option<(T, Option<String>)>, AppError> {
+    let Some(response) = get(client, url, accept, etag).await? else {
+        return Ok(None);
+    };
+
+    let etag = response_etag(&response);
+    let body = body::read_body(response, client.max_body_bytes).await?;
+    let body = serde_json::from_slice(&body).map_err(|e| {
+        AppError::FeedParsingError(format!("Unexpected response from {}: {}", url, e))
+    })?;
+
+    Ok(Some((body, etag)))
+}
+
+/// Read a page of a source as text, within the size limit
+async fn read_text(client: &SourceClient, response: Response) -> Result<String, AppError> {
+    let content_type = body::content_type(&response);
+    let body = body::read_body(response, client.max_body_bytes).await?;
+    body::decode_page(&body, content_type.as_deref())
+}
+
+fn response_etag(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Parse a feed URL, naming the source it was meant for when it is invalid
+fn parse_url(url: &str, source: &str) -> Result<Url, AppError> {
+    Url::parse(url)
+        .map_err(|e| AppError::ValidationError(format!("Invalid {} URL {}: {}", source, url, e)))
+}
+
+/// Non-empty path segments of a URL
+fn path_segments(url: &Url) -> Vec<&str> {
+    url.path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// Title for text-only posts: the first line of their text, shortened
+fn title_from_text(text: &str) -> String {
+    const MAX_TITLE_CHARS: usize = 80;
+
+    let line = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    let line = line.trim();
+    if line.is_empty() {
+        return "Untitled".to_string();
+    }
+    if line.chars().count() <= MAX_TITLE_CHARS {
+        return line.to_string();
+    }
+    let cut: String = line.chars().take(MAX_TITLE_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
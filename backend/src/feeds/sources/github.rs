@@ -0,0 +1,221 @@
+use super::{fetched, get_json, parse_url, path_segments, FeedSource, SourceClient, SourceItem};
+use crate::{
+    error::AppError,
+    feeds::{Author, FeedMetadata, FetchOutcome},
+    models::Feed,
+};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use serde::Deserialize;
+
+/// Asks the API to render release notes from Markdown to HTML
+const GITHUB_HTML: &str = "application/vnd.github.html+json";
+
+/// Releases of a GitHub (or GitHub Enterprise) repository
+pub struct GithubReleases {
+    client: SourceClient,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    html_url: String,
+    tag_name: String,
+    name: Option<String>,
+    body_html: Option<String>,
+    draft: bool,
+    prerelease: bool,
+    created_at: Option<DateTime<Utc>>,
+    published_at: Option<DateTime<Utc>>,
+    author: Option<User>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Repository {
+    full_name: String,
+    description: Option<String>,
+    html_url: String,
+    owner: User,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    login: String,
+    html_url: Option<String>,
+    avatar_url: Option<String>,
+}
+
+impl GithubReleases {
+    pub fn new(client: SourceClient) -> Self {
+        Self { client }
+    }
+
+    /// API address of the repository behind a `https://github.com/owner/repo` URL
+    fn repository_api_url(url: &str) -> Result<String, AppError> {
+        let url = parse_url(url, "GitHub repository")?;
+        let segments = path_segments(&url);
+        let [owner, repo, ..] = segments.as_slice() else {
+            return Err(AppError::ValidationError(format!(
+                "{} does not point to a GitHub repository",
+                url
+            )));
+        };
+
+        // github.com has its own API host, Enterprise servers serve it under /api/v3
+        let api = match url.host_str() {
+            Some("github.com" | "www.github.com") => "https://api.github.com".to_string(),
+            _ => format!("{}/api/v3", url.origin().ascii_serialization()),
+        };
+        Ok(format!(
+            "{}/repos/{}/{}",
+            api,
+            owner,
+            repo.trim_end_matches(".git")
+        ))
+    }
+
+    async fn fetch_releases(&self, feed: &Feed) -> Result<FetchOutcome, AppError> {
+        let url = format!(
+            "{}/releases?per_page=30",
+            Self::repository_api_url(&feed.url)?
+        );
+        let Some((releases, etag)) =
+            get_json::<Vec<Release>>(&self.client, &url, GITHUB_HTML, feed.etag.as_deref()).await?
+        else {
+            return Ok(FetchOutcome::NotModified { moved_to: None });
+        };
+
+        let items = releases
+            .into_iter()
+            .filter(|release| !release.draft)
+            .map(|release| SourceItem {
+                guid: release.html_url.clone(),
+                title: release
+                    .name
+                    .filter(|name| !name.trim().is_empty())
+                    .unwrap_or(release.tag_name),
+                url: release.html_url,
+                html: release.body_html.unwrap_or_default(),
+                published_at: release.published_at.or(release.created_at),
+                authors: release
+                    .author
+                    .map(|author| Author {
+                        name: author.login,
+                        email: None,
+                        uri: author.html_url,
+                    })
+                    .into_iter()
+                    .collect(),
+                categories: if release.prerelease {
+                    vec!["prerelease".to_string()]
+                } else {
+                    Vec::new()
+                },
+                ..Default::default()
+            })
+            .collect();
+
+        Ok(fetched(feed, items, etag))
+    }
+
+    async fn repository_metadata(&self, url: &str) -> Result<FeedMetadata, AppError> {
+        let api_url = Self::repository_api_url(url)?;
+        let (repository, _) = get_json::<Repository>(&self.client, &api_url, GITHUB_HTML, None)
+            .await?
+            .ok_or_else(|| AppError::FeedParsingError(format!("No repository at {}", url)))?;
+
+        Ok(FeedMetadata {
+            title: Some(format!("{} releases", repository.full_name)),
+            description: repository.description,
+            site_url: Some(repository.html_url),
+            icon_url: repository.owner.avatar_url,
+            ..Default::default()
+        })
+    }
+}
+
+impl FeedSource for GithubReleases {
+    fn fetch<'a>(&'a self, feed: &'a Feed) -> BoxFuture<'a, Result<FetchOutcome, AppError>> {
+        self.fetch_releases(feed).boxed()
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FeedMetadata, AppError>> {
+        self.repository_metadata(url).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::tests::{entries, test_feed};
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_fetch_releases() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/repos/laune/reader/releases"))
+            .and(header("accept", GITHUB_HTML))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"r1\"")
+                    .set_body_json(serde_json::json!([
+                        {
+                            "html_url": "https://github.com/laune/reader/releases/tag/v1.1.0-rc1",
+                            "tag_name": "v1.1.0-rc1",
+                            "name": "",
+                            "body_html": "<p>Release candidate</p>",
+                            "draft": false,
+                            "prerelease": true,
+                            "created_at": "2024-06-02T10:00:00Z",
+                            "published_at": "2024-06-02T11:00:00Z",
+                            "author": {"login": "octocat", "html_url": "https://github.com/octocat"}
+                        },
+                        {
+                            "html_url": "https://github.com/laune/reader/releases/tag/untagged-1",
+                            "tag_name": "v2.0.0",
+                            "name": "Unfinished",
+                            "body_html": null,
+                            "draft": true,
+                            "prerelease": false,
+                            "created_at": null,
+                            "published_at": null,
+                            "author": null
+                        }
+                    ])),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let feed = test_feed(format!("{}/laune/reader", server.uri()));
+        let source = GithubReleases::new(SourceClient::default());
+        let (etag, entries) = entries(source.fetch(&feed).await.unwrap());
+
+        assert_eq!(etag.as_deref(), Some("\"r1\""));
+        assert_eq!(entries.len(), 1);
+        let release = &entries[0];
+        assert_eq!(release.article.title, "v1.1.0-rc1");
+        assert_eq!(
+            release.article.guid,
+            "https://github.com/laune/reader/releases/tag/v1.1.0-rc1"
+        );
+        assert_eq!(
+            release.article.content_text.as_deref(),
+            Some("Release candidate")
+        );
+        assert_eq!(release.authors[0].name, "octocat");
+        assert_eq!(release.categories, vec!["prerelease"]);
+    }
+
+    #[test]
+    fn test_repository_api_url() {
+        assert_eq!(
+            GithubReleases::repository_api_url("https://github.com/rust-lang/rust.git/").unwrap(),
+            "https://api.github.com/repos/rust-lang/rust"
+        );
+        assert!(GithubReleases::repository_api_url("https://github.com/rust-lang").is_err());
+    }
+}
@@ -0,0 +1,237 @@
+use super::{escape_html, fetched, get_json, parse_url, title_from_text};
+use super::{FeedSource, SourceClient, SourceItem, JSON};
+use crate::{
+    content::html_to_text,
+    error::AppError,
+    feeds::{Author, FeedMetadata, FetchOutcome},
+    models::Feed,
+};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use reqwest::Url;
+use serde::Deserialize;
+
+const ITEM_URL: &str = "https://news.ycombinator.com/item?id=";
+const USER_URL: &str = "https://news.ycombinator.com/user?id=";
+
+/// A Hacker News search through the Algolia API, newest first
+pub struct HackerNews {
+    client: SourceClient,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResults {
+    hits: Vec<Hit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Hit {
+    #[serde(rename = "objectID")]
+    object_id: String,
+    title: Option<String>,
+    story_title: Option<String>,
+    url: Option<String>,
+    author: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+    story_text: Option<String>,
+    comment_text: Option<String>,
+    points: Option<i64>,
+    num_comments: Option<i64>,
+}
+
+impl HackerNews {
+    pub fn new(client: SourceClient) -> Self {
+        Self { client }
+    }
+
+    /// API address of a search.
+    ///
+    /// Both API URLs and the search page's own (`https://hn.algolia.com/?query=rust&type=story`)
+    /// are accepted, the latter keeps its `query` and `type`.
+    fn search_api_url(url: &str) -> Result<Url, AppError> {
+        let url = parse_url(url, "Hacker News search")?;
+        if url.path().starts_with("/api/") {
+            return Ok(url);
+        }
+
+        let mut api_url = url
+            .join("/api/v1/search_by_date")
+            .map_err(|e| AppError::ValidationError(e.to_string()))?;
+        let query = Self::query(&url).unwrap_or_default();
+        let tags = url
+            .query_pairs()
+            .find(|(key, _)| key == "type")
+            .map(|(_, value)| value.into_owned())
+            .unwrap_or_else(|| "story".to_string());
+        {
+            let mut pairs = api_url.query_pairs_mut();
+            pairs.append_pair("query", &query);
+            if tags != "all" {
+                pairs.append_pair("tags", &tags);
+            }
+        }
+        Ok(api_url)
+    }
+
+    fn query(url: &Url) -> Option<String> {
+        url.query_pairs()
+            .find(|(key, _)| key == "query")
+            .map(|(_, value)| value.into_owned())
+            .filter(|query| !query.trim().is_empty())
+    }
+
+    async fn fetch_hits(&self, feed: &Feed) -> Result<FetchOutcome, AppError> {
+        let api_url = Self::search_api_url(&feed.url)?;
+        let Some((results, etag)) =
+            get_json::<SearchResults>(&self.client, api_url.as_str(), JSON, feed.etag.as_deref())
+                .await?
+        else {
+            return Ok(FetchOutcome::NotModified { moved_to: None });
+        };
+
+        let items = results.hits.into_iter().map(Self::hit_item).collect();
+        Ok(fetched(feed, items, etag))
+    }
+
+    fn hit_item(hit: Hit) -> SourceItem {
+        let discussion = format!("{}{}", ITEM_URL, hit.object_id);
+        let text = hit.story_text.or(hit.comment_text);
+
+        let mut html = match (&text, &hit.url) {
+            (Some(text), _) => text.clone(),
+            (None, Some(url)) => format!(r#"<p><a href="{0}">{0}</a></p>"#, escape_html(url)),
+            (None, None) => String::new(),
+        };
+        html.push_str(&format!(
+            r#"<p>{} points, {} comments: <a href="{}">discussion</a></p>"#,
+            hit.points.unwrap_or_default(),
+            hit.num_comments.unwrap_or_default(),
+            discussion
+        ));
+
+        // Comments have no title of their own
+        let title = hit
+            .title
+            .filter(|title| !title.trim().is_empty())
+            .or_else(|| {
+                text.as_deref()
+                    .map(|text| title_from_text(&html_to_text(text)))
+            })
+            .or(hit.story_title)
+            .unwrap_or_else(|| "Untitled".to_string());
+
+        SourceItem {
+            guid: discussion.clone(),
+            title,
+            url: hit.url.unwrap_or(discussion),
+            html,
+            published_at: hit.created_at,
+            authors: hit
+                .author
+                .map(|author| Author {
+                    uri: Some(format!("{}{}", USER_URL, author)),
+                    name: author,
+                    email: None,
+                })
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    async fn search_metadata(&self, url: &str) -> Result<FeedMetadata, AppError> {
+        let api_url = Self::search_api_url(url)?;
+        get_json::<SearchResults>(&self.client, api_url.as_str(), JSON, None).await?;
+
+        let title = match Self::query(&api_url) {
+            Some(query) => format!("Hacker News: {}", query),
+            None => "Hacker News".to_string(),
+        };
+        Ok(FeedMetadata {
+            title: Some(title),
+            site_url: Some("https://news.ycombinator.com/".to_string()),
+            ..Default::default()
+        })
+    }
+}
+
+impl FeedSource for HackerNews {
+    fn fetch<'a>(&'a self, feed: &'a Feed) -> BoxFuture<'a, Result<FetchOutcome, AppError>> {
+        self.fetch_hits(feed).boxed()
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FeedMetadata, AppError>> {
+        self.search_metadata(url).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::tests::{entries, test_feed};
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_fetch_hits() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/search_by_date"))
+            .and(query_param("query", "rust"))
+            .and(query_param("tags", "story"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "hits": [
+                    {
+                        "objectID": "1",
+                        "title": "Show HN: A feed reader in Rust",
+                        "url": "https://laune.example/",
+                        "author": "pg",
+                        "created_at": "2024-06-02T10:00:00Z",
+                        "story_text": null,
+                        "points": 120,
+                        "num_comments": 33
+                    },
+                    {
+                        "objectID": "2",
+                        "title": "Ask HN: Why Rust?",
+                        "url": null,
+                        "author": "dang",
+                        "created_at": "2024-06-02T11:00:00Z",
+                        "story_text": "<p>Curious</p>",
+                        "points": 5,
+                        "num_comments": 1
+                    }
+                ]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let feed = test_feed(format!("{}/?query=rust", server.uri()));
+        let (_, entries) = entries(
+            HackerNews::new(SourceClient::default())
+                .fetch(&feed)
+                .await
+                .unwrap(),
+        );
+
+        assert_eq!(entries.len(), 2);
+        let (show, ask) = (&entries[0], &entries[1]);
+        assert_eq!(show.article.url, "https://laune.example/");
+        assert_eq!(show.article.guid, format!("{}1", ITEM_URL));
+        assert!(show
+            .article
+            .content_text
+            .as_deref()
+            .unwrap()
+            .contains("120 points"));
+        assert_eq!(ask.article.url, format!("{}2", ITEM_URL));
+        assert!(ask.article.content.starts_with("<p>Curious</p>"));
+        assert_eq!(
+            ask.authors[0].uri.as_deref(),
+            Some("https://news.ycombinator.com/user?id=dang")
+        );
+    }
+}
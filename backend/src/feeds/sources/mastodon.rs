@@ -0,0 +1,264 @@
+use super::{escape_html, fetched, get_json, parse_url, path_segments, title_from_text};
+use super::{FeedSource, SourceClient, SourceItem, JSON};
+use crate::{
+    content::html_to_text,
+    error::AppError,
+    feeds::{Author, Enclosure, FeedMetadata, FetchOutcome},
+    models::Feed,
+};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use reqwest::Url;
+use serde::Deserialize;
+
+/// Public posts of a Mastodon (or compatible) account, replies and boosts left out
+pub struct Mastodon {
+    client: SourceClient,
+}
+
+#[derive(Debug, Deserialize)]
+struct Account {
+    id: String,
+    acct: String,
+    display_name: String,
+    note: String,
+    url: String,
+    avatar: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Status {
+    uri: String,
+    url: Option<String>,
+    created_at: DateTime<Utc>,
+    edited_at: Option<DateTime<Utc>>,
+    content: String,
+    #[serde(default)]
+    spoiler_text: String,
+    account: Account,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    #[serde(default)]
+    media_attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tag {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Attachment {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    preview_url: Option<String>,
+    description: Option<String>,
+}
+
+impl Mastodon {
+    pub fn new(client: SourceClient) -> Self {
+        Self { client }
+    }
+
+    /// Instance and handle of a `https://instance/@user` profile URL
+    fn profile(url: &str) -> Result<(Url, String), AppError> {
+        let url = parse_url(url, "Mastodon profile")?;
+        let handle = path_segments(&url)
+            .first()
+            .and_then(|segment| segment.strip_prefix('@'))
+            .filter(|handle| !handle.is_empty())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                AppError::ValidationError(format!("{} is not a Mastodon profile URL", url))
+            })?;
+        Ok((url, handle))
+    }
+
+    async fn lookup_account(&self, url: &str) -> Result<(Url, Account), AppError> {
+        let (profile, handle) = Self::profile(url)?;
+        let mut lookup = profile
+            .join("/api/v1/accounts/lookup")
+            .map_err(|e| AppError::ValidationError(e.to_string()))?;
+        lookup.query_pairs_mut().append_pair("acct", &handle);
+
+        let (account, _) = get_json::<Account>(&self.client, lookup.as_str(), JSON, None)
+            .await?
+            .ok_or_else(|| AppError::FeedParsingError(format!("No account at {}", url)))?;
+        Ok((profile, account))
+    }
+
+    async fn fetch_statuses(&self, feed: &Feed) -> Result<FetchOutcome, AppError> {
+        let (profile, account) = self.lookup_account(&feed.url).await?;
+        let mut statuses_url = profile
+            .join(&format!("/api/v1/accounts/{}/statuses", account.id))
+            .map_err(|e| AppError::ValidationError(e.to_string()))?;
+        statuses_url
+            .query_pairs_mut()
+            .append_pair("exclude_replies", "true")
+            .append_pair("exclude_reblogs", "true")
+            .append_pair("limit", "40");
+
+        let Some((statuses, etag)) = get_json::<Vec<Status>>(
+            &self.client,
+            statuses_url.as_str(),
+            JSON,
+            feed.etag.as_deref(),
+        )
+        .await?
+        else {
+            return Ok(FetchOutcome::NotModified { moved_to: None });
+        };
+
+        let items = statuses.into_iter().map(Self::status_item).collect();
+        Ok(fetched(feed, items, etag))
+    }
+
+    fn status_item(status: Status) -> SourceItem {
+        // Images are shown inline, audio and video become enclosures like podcast episodes
+        let mut html = status.content;
+        let mut enclosures = Vec::new();
+        for attachment in status.media_attachments {
+            if attachment.kind == "image" {
+                html.push_str(&format!(
+                    r#"<p><img src="{}" alt="{}"></p>"#,
+                    escape_html(&attachment.url),
+                    escape_html(attachment.description.as_deref().unwrap_or_default())
+                ));
+            } else {
+                enclosures.push(Enclosure {
+                    url: attachment.url,
+                    mime_type: None,
+                    length: None,
+                    duration_seconds: None,
+                    thumbnail_url: attachment.preview_url,
+                });
+            }
+        }
+
+        // Posts have no title, a content warning is the closest thing to one
+        let title = if status.spoiler_text.trim().is_empty() {
+            title_from_text(&html_to_text(&html))
+        } else {
+            status.spoiler_text
+        };
+
+        // Mastodon's RSS feeds identify posts by their web URL, keep the same GUIDs
+        let url = status.url.unwrap_or(status.uri);
+        SourceItem {
+            guid: url.clone(),
+            title,
+            url,
+            html,
+            published_at: Some(status.created_at),
+            updated_at: status.edited_at,
+            authors: vec![Author {
+                name: if status.account.display_name.trim().is_empty() {
+                    status.account.acct
+                } else {
+                    status.account.display_name
+                },
+                email: None,
+                uri: Some(status.account.url),
+            }],
+            categories: status.tags.into_iter().map(|tag| tag.name).collect(),
+            enclosures,
+        }
+    }
+
+    async fn account_metadata(&self, url: &str) -> Result<FeedMetadata, AppError> {
+        let (_, account) = self.lookup_account(url).await?;
+        let description = html_to_text(&account.note);
+
+        Ok(FeedMetadata {
+            title: Some(if account.display_name.trim().is_empty() {
+                account.acct
+            } else {
+                account.display_name
+            }),
+            description: (!description.is_empty()).then_some(description),
+            site_url: Some(account.url),
+            icon_url: account.avatar,
+            ..Default::default()
+        })
+    }
+}
+
+impl FeedSource for Mastodon {
+    fn fetch<'a>(&'a self, feed: &'a Feed) -> BoxFuture<'a, Result<FetchOutcome, AppError>> {
+        self.fetch_statuses(feed).boxed()
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FeedMetadata, AppError>> {
+        self.account_metadata(url).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::tests::{entries, test_feed};
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_fetch_statuses() {
+        let server = MockServer::start().await;
+        let account = serde_json::json!({
+            "id": "42",
+            "acct": "ferris",
+            "display_name": "Ferris",
+            "note": "<p>Crab</p>",
+            "url": format!("{}/@ferris", server.uri()),
+            "avatar": null
+        });
+        Mock::given(method("GET"))
+            .and(path("/api/v1/accounts/lookup"))
+            .and(query_param("acct", "ferris"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&account))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/accounts/42/statuses"))
+            .and(query_param("exclude_reblogs", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                "uri": "https://social.example/users/ferris/statuses/1",
+                "url": "https://social.example/@ferris/1",
+                "created_at": "2024-06-02T10:00:00.000Z",
+                "edited_at": null,
+                "content": "<p>Hello <a href=\"https://social.example/tags/rust\">#rust</a> world</p>",
+                "spoiler_text": "",
+                "account": account,
+                "tags": [{"name": "rust"}],
+                "media_attachments": [
+                    {"type": "image", "url": "https://files.example/cat.png", "preview_url": null, "description": "A cat"},
+                    {"type": "video", "url": "https://files.example/clip.mp4", "preview_url": "https://files.example/clip.png", "description": null}
+                ]
+            }])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let feed = test_feed(format!("{}/@ferris", server.uri()));
+        let (_, entries) = entries(
+            Mastodon::new(SourceClient::default())
+                .fetch(&feed)
+                .await
+                .unwrap(),
+        );
+
+        assert_eq!(entries.len(), 1);
+        let post = &entries[0];
+        assert_eq!(post.article.guid, "https://social.example/@ferris/1");
+        assert_eq!(post.article.title, "Hello #rust world");
+        assert!(post
+            .article
+            .content
+            .contains("https://files.example/cat.png"));
+        assert_eq!(post.enclosures[0].url, "https://files.example/clip.mp4");
+        assert_eq!(post.authors[0].name, "Ferris");
+        assert_eq!(post.categories, vec!["rust"]);
+    }
+}
@@ -0,0 +1,376 @@
+use super::{FeedSource, SourceItem};
+use crate::{
+    error::AppError,
+    feeds::{content_hash, Author, FeedFetcher, FeedMetadata, FetchOutcome},
+    models::{Feed, SourceType},
+};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use mail_parser::{Message, MessageParser};
+use sqlx::{PgPool, Row};
+use tracing::info;
+
+/// Email newsletters, which arrive through the mailbox rather than by polling
+pub struct Newsletter;
+
+impl FeedSource for Newsletter {
+    fn fetch<'a>(&'a self, _feed: &'a Feed) -> BoxFuture<'a, Result<FetchOutcome, AppError>> {
+        async { Ok(FetchOutcome::NotModified { moved_to: None }) }.boxed()
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FeedMetadata, AppError>> {
+        async move {
+            let address = url
+                .strip_prefix("mailto:")
+                .filter(|address| address.contains('@'))
+                .ok_or_else(|| {
+                    AppError::ValidationError(format!(
+                        "Newsletter feeds need a mailto: URL, got {}",
+                        url
+                    ))
+                })?;
+
+            Ok(FeedMetadata {
+                title: Some(address.to_string()),
+                ..Default::default()
+            })
+        }
+        .boxed()
+    }
+}
+
+impl FeedFetcher {
+    /// Store an email as an article of its newsletter feed.
+    ///
+    /// The feed is the one for the message's recipient (a per-newsletter address)
+    /// or else its sender, senders without a feed get a new one. Returns the number
+    /// of new articles, a message that was handled before counts as none and is
+    /// not looked at again, even if its feed has been deleted since.
+    pub async fn ingest_newsletter(&self, pool: &PgPool, raw: &[u8]) -> Result<usize, AppError> {
+        let message = MessageParser::default()
+            .parse(raw)
+            .ok_or_else(|| AppError::FeedParsingError("Not an email message".to_string()))?;
+        let item = message_item(&message);
+        let guid = item.guid.clone();
+        let handled = sqlx::query("SELECT 1 FROM newsletter_messages WHERE guid = $1")
+            .bind(&guid)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?
+            .is_some();
+        if handled {
+            return Ok(0);
+        }
+
+        let sender = message
+            .from()
+            .and_then(|from| from.first())
+            .and_then(|sender| sender.address())
+            .ok_or_else(|| AppError::FeedParsingError("Email has no sender".to_string()))?;
+        let sender_name = message
+            .from()
+            .and_then(|from| from.first())
+            .and_then(|sender| sender.name())
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or(sender);
+
+        // Recipients first, a dedicated address is more specific than the sender
+        let mut addresses: Vec<String> = message
+            .to()
+            .map(|to| {
+                to.iter()
+                    .filter_map(|addr| addr.address())
+                    .map(mailto)
+                    .collect()
+            })
+            .unwrap_or_default();
+        addresses.push(mailto(sender));
+
+        let feed_id = self
+            .newsletter_feed_id(pool, &addresses, sender_name)
+            .await?;
+        let feed = self.load_feed(pool, feed_id).await?;
+        let count = if feed.active {
+            let entry = item.into_entry(&feed);
+            let saved = self.save_entries(pool, &feed, vec![entry]).await?;
+            saved.inserted.len()
+        } else {
+            info!("Ignoring newsletter for inactive feed: {}", feed.title);
+            0
+        };
+
+        // Only once stored, a failure leaves the message to be retried
+        sqlx::query("INSERT INTO newsletter_messages (guid) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(&guid)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        Ok(count)
+    }
+
+    /// ID of the newsletter feed for the first known address, creating one for the sender
+    async fn newsletter_feed_id(
+        &self,
+        pool: &PgPool,
+        addresses: &[String],
+        sender_name: &str,
+    ) -> Result<uuid::Uuid, AppError> {
+        let known =
+            sqlx::query("SELECT id, url FROM feeds WHERE source_type = $1 AND url = ANY($2)")
+                .bind(SourceType::Newsletter)
+                .bind(addresses)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        let preferred = addresses.iter().find_map(|address| {
+            known
+                .iter()
+                .find(|row| row.get::<String, _>("url") == *address)
+                .map(|row| row.get("id"))
+        });
+        if let Some(id) = preferred {
+            return Ok(id);
+        }
+
+        let sender = addresses.last().expect("sender address");
+        info!("Creating newsletter feed for {}", sender);
+        sqlx::query(
+            r#"
+            INSERT INTO feeds (title, url, source_type)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (url) DO UPDATE SET url = EXCLUDED.url
+            RETURNING id
+            "#,
+        )
+        .bind(sender_name)
+        .bind(sender)
+        .bind(SourceType::Newsletter)
+        .fetch_one(pool)
+        .await
+        .map(|row| row.get("id"))
+        .map_err(|e| AppError::DatabaseError(e.to_string()))
+    }
+}
+
+/// Feed URL of an email address
+fn mailto(address: &str) -> String {
+    format!("mailto:{}", address.trim().to_lowercase())
+}
+
+fn message_item(message: &Message) -> SourceItem {
+    let title = message
+        .subject()
+        .map(str::trim)
+        .filter(|subject| !subject.is_empty())
+        .unwrap_or("Untitled")
+        .to_string();
+    // Plain-text messages are converted to HTML by the parser
+    let html = message
+        .body_html(0)
+        .map(|html| html.into_owned())
+        .unwrap_or_default();
+
+    // Some newsletters link their web version (RFC 5064)
+    let url = message
+        .header_raw("Archived-At")
+        .map(|value| value.trim().trim_start_matches('<').trim_end_matches('>'))
+        .filter(|value| value.starts_with("http://") || value.starts_with("https://"))
+        .unwrap_or_default()
+        .to_string();
+
+    let guid = match message.message_id() {
+        Some(id) => format!("mid:{}", id),
+        None => format!("sha256:{}", content_hash(&title, &html)),
+    };
+
+    let authors = message
+        .from()
+        .and_then(|from| from.first())
+        .and_then(|sender| {
+            let address = sender.address()?;
+            Some(Author {
+                name: sender.name().unwrap_or(address).to_string(),
+                email: Some(address.to_string()),
+                uri: None,
+            })
+        })
+        .into_iter()
+        .collect();
+
+    SourceItem {
+        guid,
+        title,
+        url,
+        html,
+        published_at: message
+            .date()
+            .and_then(|date| DateTime::<Utc>::from_timestamp(date.to_timestamp(), 0)),
+        authors,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::tests::test_fetcher;
+
+    /// A plain-text email from `from` to `to`
+    fn email(from: &str, to: &str, message_id: &str) -> Vec<u8> {
+        format!(
+            concat!(
+                "From: {}\r\n",
+                "To: {}\r\n",
+                "Subject: Issue\r\n",
+                "Message-ID: <{}>\r\n",
+                "Content-Type: text/plain; charset=utf-8\r\n",
+                "\r\n",
+                "This week in Rust\r\n",
+            ),
+            from, to, message_id
+        )
+        .into_bytes()
+    }
+
+    async fn newsletter_feeds(pool: &PgPool) -> Vec<String> {
+        sqlx::query("SELECT url FROM feeds WHERE source_type = $1 ORDER BY url")
+            .bind(SourceType::Newsletter)
+            .map(|row: sqlx::postgres::PgRow| row.get("url"))
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_message_item() {
+        let raw = concat!(
+            "From: Weekly Digest <digest@news.example>\r\n",
+            "To: me+digest@example.com\r\n",
+            "Subject: Issue #12\r\n",
+            "Message-ID: <abc123@news.example>\r\n",
+            "Archived-At: <https://news.example/issues/12>\r\n",
+            "Date: Sun, 02 Jun 2024 10:00:00 +0200\r\n",
+            "MIME-Version: 1.0\r\n",
+            "Content-Type: text/html; charset=utf-8\r\n",
+            "\r\n",
+            "<p>This week in <b>Rust</b></p><img src=\"https://t.example/open.gif\" width=\"1\" height=\"1\">\r\n",
+        );
+        let message = MessageParser::default().parse(raw.as_bytes()).unwrap();
+
+        let item = message_item(&message);
+        assert_eq!(item.guid, "mid:abc123@news.example");
+        assert_eq!(item.title, "Issue #12");
+        assert_eq!(item.url, "https://news.example/issues/12");
+        assert_eq!(
+            item.published_at.unwrap().to_rfc3339(),
+            "2024-06-02T08:00:00+00:00"
+        );
+        assert_eq!(item.authors[0].name, "Weekly Digest");
+        assert_eq!(
+            item.authors[0].email.as_deref(),
+            Some("digest@news.example")
+        );
+        assert!(item.html.contains("<b>Rust</b>"));
+    }
+
+    #[sqlx::test]
+    async fn test_handled_messages_do_not_bring_back_deleted_feeds(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let raw = email("digest@news.example", "me@example.com", "1@news.example");
+
+        assert_eq!(fetcher.ingest_newsletter(&pool, &raw).await.unwrap(), 1);
+        assert_eq!(
+            newsletter_feeds(&pool).await,
+            vec!["mailto:digest@news.example"]
+        );
+
+        sqlx::query("DELETE FROM feeds WHERE source_type = $1")
+            .bind(SourceType::Newsletter)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(fetcher.ingest_newsletter(&pool, &raw).await.unwrap(), 0);
+        assert!(newsletter_feeds(&pool).await.is_empty());
+    }
+
+    async fn insert_newsletter(pool: &PgPool, url: &str, active: bool) -> uuid::Uuid {
+        sqlx::query(
+            "INSERT INTO feeds (title, url, source_type, active) VALUES ('Rust', $1, $2, $3) RETURNING id",
+        )
+        .bind(url)
+        .bind(SourceType::Newsletter)
+        .bind(active)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+        .get("id")
+    }
+
+    async fn article_count(pool: &PgPool, feed_id: uuid::Uuid) -> i64 {
+        sqlx::query("SELECT COUNT(*) AS count FROM articles WHERE feed_id = $1")
+            .bind(feed_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+            .get("count")
+    }
+
+    #[sqlx::test]
+    async fn test_newsletters_go_to_the_recipient_feed_first(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let recipient = insert_newsletter(&pool, "mailto:me+rust@example.com", true).await;
+        let sender = insert_newsletter(&pool, "mailto:digest@news.example", true).await;
+
+        let raw = email(
+            "Digest <Digest@News.example>",
+            "Me <me+rust@example.com>",
+            "1@news.example",
+        );
+        assert_eq!(fetcher.ingest_newsletter(&pool, &raw).await.unwrap(), 1);
+
+        assert_eq!(article_count(&pool, recipient).await, 1);
+        assert_eq!(article_count(&pool, sender).await, 0);
+    }
+
+    #[sqlx::test]
+    async fn test_unknown_senders_get_a_feed(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let raw = email(
+            "Weekly Digest <digest@news.example>",
+            "me@example.com",
+            "1@news.example",
+        );
+        assert_eq!(fetcher.ingest_newsletter(&pool, &raw).await.unwrap(), 1);
+
+        let feed = sqlx::query("SELECT id, title, url FROM feeds WHERE source_type = $1")
+            .bind(SourceType::Newsletter)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(feed.get::<String, _>("title"), "Weekly Digest");
+        assert_eq!(feed.get::<String, _>("url"), "mailto:digest@news.example");
+        assert_eq!(article_count(&pool, feed.get("id")).await, 1);
+    }
+
+    #[sqlx::test]
+    async fn test_inactive_newsletters_are_skipped(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let feed_id = insert_newsletter(&pool, "mailto:digest@news.example", false).await;
+
+        let raw = email("digest@news.example", "me@example.com", "1@news.example");
+        assert_eq!(fetcher.ingest_newsletter(&pool, &raw).await.unwrap(), 0);
+        assert_eq!(article_count(&pool, feed_id).await, 0);
+    }
+
+    #[sqlx::test]
+    async fn test_repeated_messages_count_as_none(pool: PgPool) {
+        let fetcher = test_fetcher();
+        let feed_id = insert_newsletter(&pool, "mailto:digest@news.example", true).await;
+
+        let raw = email("digest@news.example", "me@example.com", "1@news.example");
+        assert_eq!(fetcher.ingest_newsletter(&pool, &raw).await.unwrap(), 1);
+        assert_eq!(fetcher.ingest_newsletter(&pool, &raw).await.unwrap(), 0);
+        assert_eq!(article_count(&pool, feed_id).await, 1);
+    }
+}
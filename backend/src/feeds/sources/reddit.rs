@@ -0,0 +1,251 @@
+use super::{
+    escape_html, fetched, get_json, parse_url, FeedSource, SourceClient, SourceItem, JSON,
+};
+use crate::{
+    error::AppError,
+    feeds::{Author, FeedMetadata, FetchOutcome},
+    models::Feed,
+};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use reqwest::Url;
+use serde::Deserialize;
+
+/// Posts requested per listing, Reddit's maximum is 100
+const LISTING_LIMIT: &str = "50";
+
+/// A Reddit listing such as a subreddit, its top posts or a user's submissions
+pub struct Reddit {
+    client: SourceClient,
+}
+
+#[derive(Debug, Deserialize)]
+struct Listing {
+    data: ListingData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListingData {
+    children: Vec<Thing>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Thing {
+    data: Post,
+}
+
+#[derive(Debug, Deserialize)]
+struct Post {
+    /// Fullname such as `t3_abc123`, also the GUID of Reddit's own RSS feeds
+    name: String,
+    title: String,
+    permalink: String,
+    url: Option<String>,
+    #[serde(default)]
+    is_self: bool,
+    selftext_html: Option<String>,
+    author: Option<String>,
+    created_utc: Option<f64>,
+    /// `false`, or when the post was edited
+    #[serde(default)]
+    edited: serde_json::Value,
+    link_flair_text: Option<String>,
+    post_hint: Option<String>,
+}
+
+impl Reddit {
+    pub fn new(client: SourceClient) -> Self {
+        Self { client }
+    }
+
+    /// JSON address of a listing page, e.g. `/r/rust/top?t=week` becomes `/r/rust/top.json?t=week`
+    fn listing_api_url(url: &str) -> Result<Url, AppError> {
+        let mut api_url = parse_url(url, "Reddit")?;
+        let path = format!("{}.json", api_url.path().trim_end_matches('/'));
+        api_url.set_path(&path);
+        api_url
+            .query_pairs_mut()
+            .append_pair("raw_json", "1")
+            .append_pair("limit", LISTING_LIMIT);
+        Ok(api_url)
+    }
+
+    async fn fetch_posts(&self, feed: &Feed) -> Result<FetchOutcome, AppError> {
+        let api_url = Self::listing_api_url(&feed.url)?;
+        let Some((listing, etag)) =
+            get_json::<Listing>(&self.client, api_url.as_str(), JSON, feed.etag.as_deref()).await?
+        else {
+            return Ok(FetchOutcome::NotModified { moved_to: None });
+        };
+
+        let items = listing
+            .data
+            .children
+            .into_iter()
+            .map(|thing| Self::post_item(&api_url, thing.data))
+            .collect();
+
+        Ok(fetched(feed, items, etag))
+    }
+
+    fn post_item(api_url: &Url, post: Post) -> SourceItem {
+        let permalink = api_url
+            .join(&post.permalink)
+            .map(|url| url.to_string())
+            .unwrap_or(post.permalink);
+
+        // Link posts are described by what they point to
+        let html = match (post.is_self, post.url) {
+            (true, _) | (false, None) => post.selftext_html.unwrap_or_default(),
+            (false, Some(url)) => {
+                let url = escape_html(&url);
+                if post.post_hint.as_deref() == Some("image") {
+                    format!(
+                        r#"<p><img src="{0}" alt=""></p><p><a href="{0}">{0}</a></p>"#,
+                        url
+                    )
+                } else {
+                    format!(r#"<p><a href="{0}">{0}</a></p>"#, url)
+                }
+            }
+        };
+
+        SourceItem {
+            guid: post.name,
+            title: post.title,
+            url: permalink,
+            html,
+            published_at: post
+                .created_utc
+                .and_then(|seconds| DateTime::<Utc>::from_timestamp(seconds as i64, 0)),
+            updated_at: post
+                .edited
+                .as_f64()
+                .and_then(|seconds| DateTime::<Utc>::from_timestamp(seconds as i64, 0)),
+            authors: post
+                .author
+                .filter(|author| author != "[deleted]")
+                .map(|author| Author {
+                    uri: api_url
+                        .join(&format!("/user/{}", author))
+                        .ok()
+                        .map(|url| url.to_string()),
+                    name: author,
+                    email: None,
+                })
+                .into_iter()
+                .collect(),
+            categories: post
+                .link_flair_text
+                .filter(|flair| !flair.trim().is_empty())
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    async fn listing_metadata(&self, url: &str) -> Result<FeedMetadata, AppError> {
+        let api_url = Self::listing_api_url(url)?;
+        get_json::<Listing>(&self.client, api_url.as_str(), JSON, None).await?;
+
+        let name = api_url
+            .path()
+            .trim_end_matches(".json")
+            .trim_matches('/')
+            .to_string();
+        Ok(FeedMetadata {
+            title: Some(format!("Reddit: {}", name)),
+            site_url: Some(url.to_string()),
+            ..Default::default()
+        })
+    }
+}
+
+impl FeedSource for Reddit {
+    fn fetch<'a>(&'a self, feed: &'a Feed) -> BoxFuture<'a, Result<FetchOutcome, AppError>> {
+        self.fetch_posts(feed).boxed()
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FeedMetadata, AppError>> {
+        self.listing_metadata(url).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::tests::{entries, test_feed};
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_fetch_posts() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/r/rust/top.json"))
+            .and(query_param("t", "week"))
+            .and(query_param("raw_json", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": {"children": [
+                    {"data": {
+                        "name": "t3_self",
+                        "title": "Ask: favourite crates?",
+                        "permalink": "/r/rust/comments/self/ask/",
+                        "url": "https://www.reddit.com/r/rust/comments/self/ask/",
+                        "is_self": true,
+                        "selftext_html": "<div class=\"md\"><p>Share yours</p></div>",
+                        "author": "ferris",
+                        "created_utc": 1717322400.0,
+                        "edited": false,
+                        "link_flair_text": "discussion"
+                    }},
+                    {"data": {
+                        "name": "t3_link",
+                        "title": "Rust 1.79 released",
+                        "permalink": "/r/rust/comments/link/released/",
+                        "url": "https://blog.rust-lang.org/1.79",
+                        "is_self": false,
+                        "selftext_html": null,
+                        "author": "[deleted]",
+                        "created_utc": 1717322500.0,
+                        "edited": 1717323000.0,
+                        "link_flair_text": null
+                    }}
+                ]}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let feed = test_feed(format!("{}/r/rust/top/?t=week", server.uri()));
+        let (_, entries) = entries(
+            Reddit::new(SourceClient::default())
+                .fetch(&feed)
+                .await
+                .unwrap(),
+        );
+
+        assert_eq!(entries.len(), 2);
+        let (question, link) = (&entries[0], &entries[1]);
+        assert_eq!(question.article.guid, "t3_self");
+        assert_eq!(
+            question.article.url,
+            format!("{}/r/rust/comments/self/ask/", server.uri())
+        );
+        assert_eq!(
+            question.article.content_text.as_deref(),
+            Some("Share yours")
+        );
+        assert_eq!(question.authors[0].name, "ferris");
+        assert_eq!(question.categories, vec!["discussion"]);
+
+        assert!(link
+            .article
+            .content
+            .contains("https://blog.rust-lang.org/1.79"));
+        assert!(link.authors.is_empty());
+        assert!(link.article.source_updated_at.is_some());
+    }
+}
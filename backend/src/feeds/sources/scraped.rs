@@ -0,0 +1,374 @@
+use super::{fetched, get, parse_url, read_text, response_etag, title_from_text};
+use super::{FeedSource, SourceClient, SourceItem};
+use crate::{
+    content,
+    error::AppError,
+    feeds::{content_hash, FeedFetcher, FeedMetadata, FetchOutcome},
+    models::{Feed, ScrapePreviewResponse, ScrapeSelectors, ScrapedItem},
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+
+const HTML: &str = "text/html,application/xhtml+xml";
+
+/// Date formats tried, in order, on dates that are neither RFC 3339 nor RFC 2822
+const DATETIME_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"];
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%d.%m.%Y",
+    "%B %d, %Y",
+    "%b %d, %Y",
+    "%d %B %Y",
+    "%d %b %Y",
+];
+
+/// A web page without a feed, turned into entries with CSS selectors
+pub struct ScrapedPage {
+    client: SourceClient,
+}
+
+/// `ScrapeSelectors` parsed and ready to match
+struct CompiledSelectors {
+    item: Selector,
+    title: Option<Selector>,
+    link: Option<Selector>,
+    date: Option<Selector>,
+    content: Option<Selector>,
+}
+
+impl CompiledSelectors {
+    fn compile(selectors: &ScrapeSelectors) -> Result<Self, AppError> {
+        let parse = |name: &str, selector: &str| {
+            Selector::parse(selector).map_err(|e| {
+                AppError::ValidationError(format!(
+                    "Invalid {} selector '{}': {}",
+                    name, selector, e
+                ))
+            })
+        };
+        let optional = |name: &str, selector: &Option<String>| {
+            selector
+                .as_deref()
+                .filter(|selector| !selector.trim().is_empty())
+                .map(|selector| parse(name, selector))
+                .transpose()
+        };
+
+        Ok(Self {
+            item: parse("item", &selectors.item)?,
+            title: optional("title", &selectors.title)?,
+            link: optional("link", &selectors.link)?,
+            date: optional("date", &selectors.date)?,
+            content: optional("content", &selectors.content)?,
+        })
+    }
+}
+
+impl ScrapedPage {
+    pub fn new(client: SourceClient) -> Self {
+        Self { client }
+    }
+
+    async fn fetch_page(&self, feed: &Feed) -> Result<FetchOutcome, AppError> {
+        let selectors = feed.scrape_selectors.as_ref().ok_or_else(|| {
+            AppError::ValidationError(format!("Feed {} has no scrape selectors", feed.id))
+        })?;
+
+        let Some(response) = get(&self.client, &feed.url, HTML, feed.etag.as_deref()).await? else {
+            return Ok(FetchOutcome::NotModified { moved_to: None });
+        };
+        let etag = response_etag(&response);
+        let html = read_text(&self.client, response).await?;
+
+        let items = scrape_items(&html, &feed.url, selectors)?;
+        Ok(fetched(feed, items, etag))
+    }
+
+    async fn page_metadata(&self, url: &str) -> Result<FeedMetadata, AppError> {
+        let html = self.download(url).await?;
+        Ok(page_metadata(&html, &parse_url(url, "page")?))
+    }
+
+    async fn download(&self, url: &str) -> Result<String, AppError> {
+        let response = get(&self.client, url, HTML, None)
+            .await?
+            .ok_or_else(|| AppError::FeedParsingError(format!("No page at {}", url)))?;
+        read_text(&self.client, response).await
+    }
+}
+
+impl FeedSource for ScrapedPage {
+    fn fetch<'a>(&'a self, feed: &'a Feed) -> BoxFuture<'a, Result<FetchOutcome, AppError>> {
+        self.fetch_page(feed).boxed()
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FeedMetadata, AppError>> {
+        self.page_metadata(url).boxed()
+    }
+}
+
+impl FeedFetcher {
+    /// Show what a set of selectors extracts from a page, without storing anything
+    pub async fn preview_scrape(
+        &self,
+        url: &str,
+        selectors: &ScrapeSelectors,
+    ) -> Result<ScrapePreviewResponse, AppError> {
+        let (preview, _) = self.preview_scrape_with_metadata(url, selectors).await?;
+        Ok(preview)
+    }
+
+    /// `preview_scrape`, also returning the metadata of the page it downloaded
+    pub async fn preview_scrape_with_metadata(
+        &self,
+        url: &str,
+        selectors: &ScrapeSelectors,
+    ) -> Result<(ScrapePreviewResponse, FeedMetadata), AppError> {
+        let page_url = parse_url(url, "page")?;
+        let html = ScrapedPage::new(self.source_client()).download(url).await?;
+
+        let items = scrape_items(&html, url, selectors)?
+            .into_iter()
+            .map(|item| {
+                let base_url = if item.url.is_empty() { url } else { &item.url };
+                ScrapedItem {
+                    content: content::sanitize_html(&item.html, Some(base_url)),
+                    title: item.title,
+                    url: item.url,
+                    published_at: item.published_at,
+                }
+            })
+            .collect();
+
+        let metadata = page_metadata(&html, &page_url);
+        let preview = ScrapePreviewResponse {
+            title: metadata.title.clone(),
+            items,
+        };
+        Ok((preview, metadata))
+    }
+}
+
+/// Entries of a page, one per element matching the item selector
+fn scrape_items(
+    html: &str,
+    page_url: &str,
+    selectors: &ScrapeSelectors,
+) -> Result<Vec<SourceItem>, AppError> {
+    let selectors = CompiledSelectors::compile(selectors)?;
+    let page_url = parse_url(page_url, "page")?;
+    let document = Html::parse_document(html);
+
+    let headings = Selector::parse("h1, h2, h3, h4, h5, h6").expect("valid selector");
+    let links = Selector::parse("a[href]").expect("valid selector");
+
+    let items = document
+        .select(&selectors.item)
+        .map(|item| {
+            let select = |selector: &Option<Selector>| {
+                selector
+                    .as_ref()
+                    .and_then(|selector| item.select(selector).next())
+            };
+
+            let link = match &selectors.link {
+                Some(selector) => item.select(selector).next(),
+                None if item.value().name() == "a" => Some(item),
+                None => item.select(&links).next(),
+            };
+            let url = link
+                .and_then(|link| link.value().attr("href"))
+                .and_then(|href| page_url.join(href.trim()).ok())
+                .map(|url| url.to_string())
+                .unwrap_or_default();
+
+            let html = select(&selectors.content)
+                .map(|content| content.inner_html())
+                .unwrap_or_else(|| item.inner_html());
+
+            let title = select(&selectors.title)
+                .or_else(|| item.select(&headings).next())
+                .or(link)
+                .map(element_text)
+                .filter(|title| !title.is_empty())
+                .unwrap_or_else(|| title_from_text(&content::html_to_text(&html)));
+
+            let published_at = select(&selectors.date).and_then(element_date);
+
+            // Items without a link are identified by what they say, like feed entries without an ID
+            let guid = if url.is_empty() {
+                format!("sha256:{}", content_hash(&title, &html))
+            } else {
+                url.clone()
+            };
+
+            SourceItem {
+                guid,
+                title,
+                url,
+                html,
+                published_at,
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    Ok(items)
+}
+
+/// Title, description, language and icon of a web page
+fn page_metadata(html: &str, page_url: &Url) -> FeedMetadata {
+    let document = Html::parse_document(html);
+    let first = |selector: &str| {
+        let selector = Selector::parse(selector).expect("valid selector");
+        document.select(&selector).next()
+    };
+    let attr = |selector: &str, name: &str| {
+        first(selector)
+            .and_then(|element| element.value().attr(name))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    FeedMetadata {
+        title: first("title")
+            .map(element_text)
+            .filter(|title| !title.is_empty()),
+        description: attr(r#"meta[name="description"]"#, "content")
+            .or_else(|| attr(r#"meta[property="og:description"]"#, "content")),
+        site_url: Some(page_url.to_string()),
+        language: attr("html[lang]", "lang"),
+        icon_url: attr(r#"link[rel~="icon"][href]"#, "href")
+            .and_then(|href| page_url.join(&href).ok())
+            .map(|url| url.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Date of an element, from its machine-readable attributes or its text
+fn element_date(element: ElementRef) -> Option<DateTime<Utc>> {
+    ["datetime", "content", "title"]
+        .iter()
+        .filter_map(|name| element.value().attr(name))
+        .find_map(parse_date)
+        .or_else(|| parse_date(&element_text(element)))
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    DATETIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .map(|date| date.and_utc())
+}
+
+/// Visible text of an element with whitespace collapsed
+fn element_text(element: ElementRef) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::tests::{entries, test_feed};
+    use crate::models::SourceType;
+    use sqlx::types::Json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const PAGE: &str = r#"<html lang="en"><head><title>Changelog</title></head><body>
+        <nav><a href="/">Home</a></nav>
+        <div class="post">
+            <h2><a href="/changes/2">Version 2</a></h2>
+            <time datetime="2024-06-02T10:00:00Z">June 2</time>
+            <div class="body"><p>Dark mode.</p></div>
+        </div>
+        <div class="post">
+            <h2>Version 1</h2>
+            <span class="date">May 1, 2024</span>
+            <div class="body"><p>First release.</p></div>
+        </div>
+    </body></html>"#;
+
+    fn selectors() -> ScrapeSelectors {
+        ScrapeSelectors {
+            item: "div.post".to_string(),
+            title: None,
+            link: None,
+            date: Some("time, .date".to_string()),
+            content: Some(".body".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_scraped_page() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/changelog"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(PAGE))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut feed = test_feed(format!("{}/changelog", server.uri()));
+        feed.source_type = SourceType::Scraped;
+        feed.scrape_selectors = Some(Json(selectors()));
+        let (_, entries) = entries(
+            ScrapedPage::new(SourceClient::default())
+                .fetch(&feed)
+                .await
+                .unwrap(),
+        );
+
+        assert_eq!(entries.len(), 2);
+        let (linked, unlinked) = (&entries[0].article, &entries[1].article);
+        assert_eq!(linked.title, "Version 2");
+        assert_eq!(linked.url, format!("{}/changes/2", server.uri()));
+        assert_eq!(linked.guid, linked.url);
+        assert_eq!(linked.content_text.as_deref(), Some("Dark mode."));
+        assert_eq!(
+            linked.published_at.to_rfc3339(),
+            "2024-06-02T10:00:00+00:00"
+        );
+
+        assert_eq!(unlinked.title, "Version 1");
+        assert!(unlinked.guid.starts_with("sha256:"));
+        assert_eq!(
+            unlinked.published_at.to_rfc3339(),
+            "2024-05-01T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_invalid_selector_is_rejected() {
+        let selectors = ScrapeSelectors {
+            item: "div[".to_string(),
+            ..selectors()
+        };
+        assert!(matches!(
+            scrape_items(PAGE, "https://example.com/", &selectors),
+            Err(AppError::ValidationError(_))
+        ));
+    }
+}
@@ -0,0 +1,419 @@
+use super::{
+    body, escape_html, fetched, get, parse_url, path_segments, response_etag, title_from_text,
+};
+use super::{FeedSource, SourceClient, SourceItem};
+use crate::{
+    error::AppError,
+    feeds::{FeedMetadata, FetchOutcome},
+    models::Feed,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+use futures::future::{BoxFuture, FutureExt};
+use quick_xml::{events::Event, Reader};
+use reqwest::Response;
+use std::{cmp::Reverse, io::Read};
+use tracing::warn;
+
+const XML: &str = "application/xml,text/xml;q=0.9,*/*;q=0.8";
+
+/// Sitemaps of an index that are read, the most recently modified ones
+const MAX_CHILD_SITEMAPS: usize = 3;
+
+/// URLs turned into entries per refresh, the most recent ones. Full sitemaps list
+/// a whole site, news sitemaps only the last two days.
+const MAX_SITEMAP_URLS: usize = 200;
+
+/// A site's pages listed in a sitemap (`https://example.com/news-sitemap.xml`),
+/// including Google News sitemaps and sitemap indexes
+pub struct Sitemap {
+    client: SourceClient,
+}
+
+/// What a sitemap document lists
+#[derive(Debug)]
+enum SitemapDocument {
+    /// `<sitemapindex>`, pointing at other sitemaps
+    Index(Vec<ChildSitemap>),
+    /// `<urlset>`
+    Urls(Vec<SitemapUrl>),
+}
+
+#[derive(Debug, Default)]
+struct ChildSitemap {
+    loc: String,
+    lastmod: Option<DateTime<Utc>>,
+}
+
+/// A `<url>`, with its `news:` metadata when it has any
+#[derive(Debug, Default)]
+struct SitemapUrl {
+    loc: String,
+    lastmod: Option<DateTime<Utc>>,
+    title: Option<String>,
+    publication_date: Option<DateTime<Utc>>,
+    keywords: Vec<String>,
+    publication: Option<String>,
+    language: Option<String>,
+}
+
+impl Sitemap {
+    pub fn new(client: SourceClient) -> Self {
+        Self { client }
+    }
+
+    async fn fetch_sitemap(&self, feed: &Feed) -> Result<FetchOutcome, AppError> {
+        let Some(response) = get(&self.client, &feed.url, XML, feed.etag.as_deref()).await? else {
+            return Ok(FetchOutcome::NotModified { moved_to: None });
+        };
+        let etag = response_etag(&response);
+
+        let mut urls = match self.read_sitemap(response).await? {
+            SitemapDocument::Urls(urls) => urls,
+            SitemapDocument::Index(sitemaps) => self.read_index(sitemaps).await?,
+        };
+
+        urls.sort_by_key(|url| Reverse(url.publication_date.or(url.lastmod)));
+        urls.truncate(MAX_SITEMAP_URLS);
+        let items = urls.into_iter().map(url_item).collect();
+        Ok(fetched(feed, items, etag))
+    }
+
+    /// URLs of the most recently modified sitemaps of an index
+    async fn read_index(
+        &self,
+        mut sitemaps: Vec<ChildSitemap>,
+    ) -> Result<Vec<SitemapUrl>, AppError> {
+        sitemaps.sort_by_key(|sitemap| Reverse(sitemap.lastmod));
+
+        let mut urls = Vec::new();
+        for sitemap in sitemaps.iter().take(MAX_CHILD_SITEMAPS) {
+            // One broken sitemap should not hide the others
+            let document = match get(&self.client, &sitemap.loc, XML, None).await {
+                Ok(Some(response)) => self.read_sitemap(response).await,
+                Ok(None) => continue,
+                Err(e) => Err(e),
+            };
+            match document {
+                Ok(SitemapDocument::Urls(found)) => urls.extend(found),
+                Ok(SitemapDocument::Index(_)) => {
+                    warn!("Skipping nested sitemap index {}", sitemap.loc)
+                }
+                Err(e) => warn!("Skipping sitemap {}: {}", sitemap.loc, e),
+            }
+        }
+
+        Ok(urls)
+    }
+
+    /// Read a sitemap, gzipped or not
+    async fn read_sitemap(&self, response: Response) -> Result<SitemapDocument, AppError> {
+        let content_type = body::content_type(&response);
+        let body = body::read_body(response, self.client.max_body_bytes).await?;
+        // `.xml.gz` files are served as they are, not with a `Content-Encoding`
+        let body = if body.starts_with(&[0x1f, 0x8b]) {
+            gunzip(&body, self.client.max_body_bytes)?
+        } else {
+            body
+        };
+        let xml = body::decode_feed(&body, content_type.as_deref())?;
+        parse_sitemap(&xml)
+    }
+
+    async fn sitemap_metadata(&self, url: &str) -> Result<FeedMetadata, AppError> {
+        let sitemap_url = parse_url(url, "sitemap")?;
+        let response = get(&self.client, url, XML, None)
+            .await?
+            .ok_or_else(|| AppError::FeedParsingError(format!("No sitemap at {}", url)))?;
+
+        let (publication, language) = match self.read_sitemap(response).await? {
+            SitemapDocument::Urls(urls) => urls
+                .into_iter()
+                .find(|url| url.publication.is_some())
+                .map(|url| (url.publication, url.language))
+                .unwrap_or_default(),
+            SitemapDocument::Index(_) => (None, None),
+        };
+
+        let host = sitemap_url.host_str().unwrap_or_default();
+        Ok(FeedMetadata {
+            title: Some(publication.unwrap_or_else(|| host.to_string())),
+            site_url: sitemap_url.join("/").ok().map(String::from),
+            language,
+            ..Default::default()
+        })
+    }
+}
+
+impl FeedSource for Sitemap {
+    fn fetch<'a>(&'a self, feed: &'a Feed) -> BoxFuture<'a, Result<FetchOutcome, AppError>> {
+        self.fetch_sitemap(feed).boxed()
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FeedMetadata, AppError>> {
+        self.sitemap_metadata(url).boxed()
+    }
+}
+
+/// Sitemap URLs have nothing but a link until their page is extracted
+fn url_item(url: SitemapUrl) -> SourceItem {
+    SourceItem {
+        guid: url.loc.clone(),
+        title: url
+            .title
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| title_from_url(&url.loc)),
+        html: format!(r#"<p><a href="{0}">{0}</a></p>"#, escape_html(&url.loc)),
+        url: url.loc,
+        published_at: url.publication_date,
+        updated_at: url.lastmod,
+        categories: url.keywords,
+        ..Default::default()
+    }
+}
+
+/// Read a `<urlset>` or `<sitemapindex>`, ignoring extensions other than `news:`
+fn parse_sitemap(xml: &[u8]) -> Result<SitemapDocument, AppError> {
+    let mut reader = Reader::from_reader(xml);
+    reader.trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut document = None;
+    let mut url = SitemapUrl::default();
+    let mut sitemap = ChildSitemap::default();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                if path.is_empty() {
+                    document = match name.as_str() {
+                        "urlset" => Some(SitemapDocument::Urls(Vec::new())),
+                        "sitemapindex" => Some(SitemapDocument::Index(Vec::new())),
+                        _ => None,
+                    };
+                }
+                path.push(name);
+                text.clear();
+            }
+            Ok(Event::Text(e)) => text.push_str(&e.unescape().unwrap_or_default()),
+            Ok(Event::CData(e)) => text.push_str(&String::from_utf8_lossy(&e.into_inner())),
+            Ok(Event::End(_)) => {
+                let value = text.trim();
+                match path.iter().rev().take(2).collect::<Vec<_>>().as_slice() {
+                    [element, _] if *element == "url" => {
+                        let url = std::mem::take(&mut url);
+                        if let Some(SitemapDocument::Urls(urls)) = &mut document {
+                            if !url.loc.is_empty() {
+                                urls.push(url);
+                            }
+                        }
+                    }
+                    [element, _] if *element == "sitemap" => {
+                        let sitemap = std::mem::take(&mut sitemap);
+                        if let Some(SitemapDocument::Index(sitemaps)) = &mut document {
+                            if !sitemap.loc.is_empty() {
+                                sitemaps.push(sitemap);
+                            }
+                        }
+                    }
+                    [element, parent] if *element == "loc" && *parent == "url" => {
+                        url.loc = value.to_string()
+                    }
+                    [element, parent] if *element == "loc" && *parent == "sitemap" => {
+                        sitemap.loc = value.to_string()
+                    }
+                    [element, parent] if *element == "lastmod" && *parent == "url" => {
+                        url.lastmod = w3c_date(value)
+                    }
+                    [element, parent] if *element == "lastmod" && *parent == "sitemap" => {
+                        sitemap.lastmod = w3c_date(value)
+                    }
+                    [element, parent] if *element == "title" && *parent == "news" => {
+                        url.title = Some(value.to_string())
+                    }
+                    [element, parent] if *element == "publication_date" && *parent == "news" => {
+                        url.publication_date = w3c_date(value)
+                    }
+                    [element, parent] if *element == "keywords" && *parent == "news" => {
+                        url.keywords = value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|keyword| !keyword.is_empty())
+                            .map(str::to_string)
+                            .collect()
+                    }
+                    [element, parent] if *element == "name" && *parent == "publication" => {
+                        url.publication = Some(value.to_string())
+                    }
+                    [element, parent] if *element == "language" && *parent == "publication" => {
+                        url.language = Some(value.to_string())
+                    }
+                    _ => {}
+                }
+                path.pop();
+                text.clear();
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                return Err(AppError::FeedParsingError(format!(
+                    "Failed to parse sitemap: {}",
+                    e
+                )))
+            }
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    document.ok_or_else(|| {
+        AppError::FeedParsingError("The document is not a sitemap or sitemap index".to_string())
+    })
+}
+
+/// Decompress a gzipped sitemap, within the size limit
+fn gunzip(body: &[u8], max_bytes: usize) -> Result<Vec<u8>, AppError> {
+    let mut xml = Vec::new();
+    GzDecoder::new(body)
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut xml)
+        .map_err(|e| AppError::UnreadableDocument(format!("Invalid gzipped sitemap: {}", e)))?;
+    if xml.len() > max_bytes {
+        return Err(AppError::UnreadableDocument(format!(
+            "The sitemap is larger than the {} byte limit once decompressed",
+            max_bytes
+        )));
+    }
+    Ok(xml)
+}
+
+/// W3C datetime of sitemaps: a full RFC 3339 timestamp or a plain date
+fn w3c_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            // Minutes without seconds, e.g. `2024-06-02T10:30+02:00`
+            DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M%:z")
+                .map(|date| date.with_timezone(&Utc))
+                .ok()
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|date| date.and_utc())
+        })
+}
+
+/// Title for a page known only by its URL, from the slug of its path
+fn title_from_url(url: &str) -> String {
+    let slug = parse_url(url, "page").ok().and_then(|url| {
+        path_segments(&url).last().map(|segment| {
+            let name = segment.split('.').next().unwrap_or(segment);
+            name.replace(['-', '_'], " ")
+        })
+    });
+
+    match slug.filter(|slug| slug.chars().any(char::is_alphabetic)) {
+        Some(slug) => {
+            let mut chars = slug.trim().chars();
+            let title: String = chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default();
+            title_from_text(&title)
+        }
+        None => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::tests::{entries, test_feed};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_parse_news_sitemap() {
+        let xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9"
+        xmlns:news="http://www.google.com/schemas/sitemap-news/0.9"
+        xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">
+  <url>
+    <loc>https://example.com/2024/06/02/rates-rise</loc>
+    <lastmod>2024-06-02T12:00:00+00:00</lastmod>
+    <image:image><image:loc>https://example.com/img.jpg</image:loc><image:title>Photo</image:title></image:image>
+    <news:news>
+      <news:publication><news:name>Example Times</news:name><news:language>en</news:language></news:publication>
+      <news:publication_date>2024-06-02T10:30+02:00</news:publication_date>
+      <news:title><![CDATA[Rates rise & markets fall]]></news:title>
+      <news:keywords>economy, rates</news:keywords>
+    </news:news>
+  </url>
+  <url><loc>https://example.com/about-us.html</loc><lastmod>2024-01-15</lastmod></url>
+</urlset>"#;
+
+        let SitemapDocument::Urls(urls) = parse_sitemap(xml).unwrap() else {
+            panic!("expected a urlset");
+        };
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0].title.as_deref(), Some("Rates rise & markets fall"));
+        assert_eq!(
+            urls[0].publication_date.unwrap().to_rfc3339(),
+            "2024-06-02T08:30:00+00:00"
+        );
+        assert_eq!(urls[0].keywords, vec!["economy", "rates"]);
+        assert_eq!(urls[0].publication.as_deref(), Some("Example Times"));
+        assert_eq!(
+            urls[1].lastmod.unwrap().to_rfc3339(),
+            "2024-01-15T00:00:00+00:00"
+        );
+        assert_eq!(title_from_url(&urls[1].loc), "About us");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sitemap_index() {
+        let server = MockServer::start().await;
+        let index = format!(
+            r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+            <sitemap><loc>{0}/old.xml</loc><lastmod>2020-01-01</lastmod></sitemap>
+            <sitemap><loc>{0}/posts.xml</loc><lastmod>2024-06-02</lastmod></sitemap>
+            </sitemapindex>"#,
+            server.uri()
+        );
+        Mock::given(method("GET"))
+            .and(path("/sitemap.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(index))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/posts.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+                <url><loc>https://example.com/first-post</loc><lastmod>2024-06-01</lastmod></url>
+                <url><loc>https://example.com/second-post</loc><lastmod>2024-06-02</lastmod></url>
+                </urlset>"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/old.xml"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let feed = test_feed(format!("{}/sitemap.xml", server.uri()));
+        let source = Sitemap::new(SourceClient::default());
+        let (_, entries) = entries(source.fetch(&feed).await.unwrap());
+
+        let titles: Vec<_> = entries.iter().map(|e| e.article.title.as_str()).collect();
+        assert_eq!(titles, vec!["Second post", "First post"]);
+        assert_eq!(entries[0].article.guid, "https://example.com/second-post");
+    }
+}
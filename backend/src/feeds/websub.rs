@@ -0,0 +1,577 @@
+use super::{body, feed_parser, guard, FeedFetcher};
+use crate::{error::AppError, models::WebSubVerification};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng};
+use sha1::Sha1;
+use sha2::{Sha256, Sha384, Sha512};
+use sqlx::{PgPool, Row};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Minutes to wait for a hub to verify a subscription before asking again
+const VERIFICATION_TIMEOUT_MINUTES: i64 = 60;
+
+/// Range a lease granted by a hub is clamped to, it comes from the query string
+const MIN_LEASE_SECONDS: i64 = 60;
+const MAX_LEASE_SECONDS: i64 = 365 * 86_400;
+
+impl FeedFetcher {
+    /// Whether a public callback URL is configured, without one hubs cannot reach us
+    pub fn websub_enabled(&self) -> bool {
+        !self.websub.callback_base_url.trim().is_empty()
+    }
+
+    /// Ask a hub to push updates of `topic` to this server.
+    ///
+    /// The subscription is only active once the hub has verified it through the
+    /// callback, see `verify_websub`.
+    pub async fn subscribe_websub(
+        &self,
+        pool: &PgPool,
+        feed_id: Uuid,
+        hub: &str,
+        topic: &str,
+    ) -> Result<(), AppError> {
+        let secret: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(40)
+            .map(char::from)
+            .collect();
+
+        sqlx::query(
+            r#"
+            UPDATE feeds
+            SET websub_hub = $1, websub_topic = $2, websub_secret = $3,
+                websub_requested_at = NOW()
+            WHERE id = $4
+            "#,
+        )
+        .bind(hub)
+        .bind(topic)
+        .bind(&secret)
+        .bind(feed_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        info!("Subscribing feed {} to WebSub hub {}", feed_id, hub);
+        let callback = self.websub_callback(feed_id);
+        self.send_subscription_request(hub, topic, &callback, Some(&secret))
+            .await
+    }
+
+    /// Ask the feed's hub to stop pushing updates, if it has a subscription.
+    ///
+    /// The subscription is cleared first, so `verify_websub` confirms the hub's
+    /// verification request even once the feed is deleted.
+    pub async fn unsubscribe_websub(&self, pool: &PgPool, feed_id: Uuid) -> Result<(), AppError> {
+        let subscription = sqlx::query("SELECT websub_hub, websub_topic FROM feeds WHERE id = $1")
+            .bind(feed_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?
+            .map(|row| {
+                let hub: Option<String> = row.get("websub_hub");
+                let topic: Option<String> = row.get("websub_topic");
+                (hub, topic)
+            });
+        let Some((Some(hub), Some(topic))) = subscription else {
+            return Ok(());
+        };
+
+        self.clear_websub(pool, feed_id).await?;
+
+        info!("Unsubscribing feed {} from WebSub hub {}", feed_id, hub);
+        let callback = self.websub_callback(feed_id);
+        self.send_subscription_request(&hub, &topic, &callback, None)
+            .await
+    }
+
+    /// POST a subscription request to the hub, unsubscribing when no secret is given
+    async fn send_subscription_request(
+        &self,
+        hub: &str,
+        topic: &str,
+        callback: &str,
+        secret: Option<&str>,
+    ) -> Result<(), AppError> {
+        let lease_seconds = self.websub.lease_seconds.to_string();
+        let form: Vec<(&str, &str)> = match secret {
+            Some(secret) => vec![
+                ("hub.mode", "subscribe"),
+                ("hub.topic", topic),
+                ("hub.callback", callback),
+                ("hub.secret", secret),
+                ("hub.lease_seconds", lease_seconds.as_str()),
+            ],
+            None => vec![
+                ("hub.mode", "unsubscribe"),
+                ("hub.topic", topic),
+                ("hub.callback", callback),
+            ],
+        };
+
+        self.guard.check_url(hub)?;
+        let response = self
+            .client
+            .post(hub)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| guard::request_error("Failed to reach WebSub hub", e))?;
+
+        if !response.status().is_success() {
+            return Err(AppError::FeedParsingError(format!(
+                "WebSub hub rejected the {} request. Status: {}",
+                form[0].1,
+                response.status()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Answer a hub's verification request.
+    ///
+    /// Returns the body to respond with, or `None` when the request does not match
+    /// a subscription we asked for.
+    pub async fn verify_websub(
+        &self,
+        pool: &PgPool,
+        feed_id: Uuid,
+        verification: &WebSubVerification,
+    ) -> Result<Option<String>, AppError> {
+        // The topic is cleared together with the hub, so it alone tells whether we subscribed
+        let topic: Option<String> = sqlx::query("SELECT websub_topic FROM feeds WHERE id = $1")
+            .bind(feed_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?
+            .and_then(|row| row.get("websub_topic"));
+        let subscribed = topic.as_deref() == Some(verification.topic.as_str());
+
+        match verification.mode.as_str() {
+            "subscribe" if subscribed => {
+                let lease_seconds = verification
+                    .lease_seconds
+                    .unwrap_or(self.websub.lease_seconds as i64)
+                    .clamp(MIN_LEASE_SECONDS, MAX_LEASE_SECONDS);
+                sqlx::query("UPDATE feeds SET websub_lease_expires_at = $1 WHERE id = $2")
+                    .bind(Utc::now() + Duration::seconds(lease_seconds))
+                    .bind(feed_id)
+                    .execute(pool)
+                    .await
+                    .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+                info!(
+                    "WebSub subscription of feed {} verified for {} seconds",
+                    feed_id, lease_seconds
+                );
+                Ok(verification.challenge.clone())
+            }
+            // Confirm unsubscribing from anything we no longer follow
+            "unsubscribe" if !subscribed => Ok(verification.challenge.clone()),
+            "denied" if subscribed => {
+                warn!(
+                    "WebSub hub denied the subscription of feed {}: {}",
+                    feed_id,
+                    verification.reason.as_deref().unwrap_or("no reason given")
+                );
+                self.clear_websub(pool, feed_id).await?;
+                Ok(Some(String::new()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Ingest content pushed by a hub, through the same path as a refresh.
+    ///
+    /// Content whose signature does not match the subscription secret is ignored,
+    /// as the spec requires, but still acknowledged.
+    pub async fn receive_websub(
+        &self,
+        pool: &PgPool,
+        feed_id: Uuid,
+        signature: Option<&str>,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> Result<usize, AppError> {
+        let secret: Option<String> =
+            sqlx::query("SELECT websub_secret FROM feeds WHERE id = $1 AND websub_hub IS NOT NULL")
+                .bind(feed_id)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| AppError::DatabaseError(e.to_string()))?
+                .ok_or_else(|| {
+                    AppError::NotFound(format!("Feed {} has no WebSub subscription", feed_id))
+                })?
+                .get("websub_secret");
+
+        if let Some(secret) = secret {
+            if !signature.is_some_and(|signature| verify_signature(&secret, signature, body)) {
+                warn!(
+                    "Ignoring WebSub content for feed {} with a bad signature",
+                    feed_id
+                );
+                return Ok(0);
+            }
+        }
+
+        let feed = self.load_feed(pool, feed_id).await?;
+        if !feed.active {
+            info!("Ignoring WebSub content for inactive feed: {}", feed.title);
+            return Ok(0);
+        }
+
+        let xml = body::decode_feed(body, content_type)?;
+        let parsed_feed = feed_parser(&feed.url)
+            .parse(xml.as_ref())
+            .map_err(|e| AppError::FeedParsingError(format!("Failed to parse XML: {}", e)))?;
+        let entries = self.rss_to_articles(&feed, parsed_feed)?;
+
+        info!(
+            "Received {} pushed entries for feed: {}",
+            entries.len(),
+            feed.title
+        );
+        let saved = self.save_entries(pool, &feed, entries).await?;
+        Ok(saved.inserted.len())
+    }
+
+    /// Renew subscriptions whose lease is about to run out, and retry ones the
+    /// hub never verified
+    pub async fn renew_websub_leases(&self, pool: &PgPool) -> Result<(), AppError> {
+        let due = sqlx::query(
+            r#"
+            SELECT id, title, websub_hub, websub_topic FROM feeds
+            WHERE active = true AND websub_hub IS NOT NULL
+            AND (websub_lease_expires_at IS NULL OR websub_lease_expires_at <= $1)
+            AND (websub_requested_at IS NULL OR websub_requested_at <= $2)
+            "#,
+        )
+        .bind(Utc::now() + Duration::seconds(self.websub.renew_before_seconds as i64))
+        .bind(Utc::now() - Duration::minutes(VERIFICATION_TIMEOUT_MINUTES))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        for row in due {
+            let feed_id: Uuid = row.get("id");
+            let title: String = row.get("title");
+            let hub: String = row.get("websub_hub");
+            let topic: String = row.get("websub_topic");
+
+            if let Err(e) = self.subscribe_websub(pool, feed_id, &hub, &topic).await {
+                error!("Failed to renew WebSub lease of '{}': {}", title, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn clear_websub(&self, pool: &PgPool, feed_id: Uuid) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE feeds
+            SET websub_hub = NULL, websub_topic = NULL, websub_secret = NULL,
+                websub_requested_at = NULL, websub_lease_expires_at = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(feed_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn websub_callback(&self, feed_id: Uuid) -> String {
+        format!(
+            "{}/api/websub/{}",
+            self.websub.callback_base_url.trim().trim_end_matches('/'),
+            feed_id
+        )
+    }
+}
+
+/// Check an `X-Hub-Signature` header (`method=hexdigest`) against the body
+fn verify_signature(secret: &str, signature: &str, body: &[u8]) -> bool {
+    let Some((method, digest)) = signature.split_once('=') else {
+        return false;
+    };
+    let Some(digest) = decode_hex(digest.trim()) else {
+        return false;
+    };
+
+    match method.trim().to_ascii_lowercase().as_str() {
+        "sha1" => verify_hmac::<Hmac<Sha1>>(secret, body, &digest),
+        "sha256" => verify_hmac::<Hmac<Sha256>>(secret, body, &digest),
+        "sha384" => verify_hmac::<Hmac<Sha384>>(secret, body, &digest),
+        "sha512" => verify_hmac::<Hmac<Sha512>>(secret, body, &digest),
+        _ => false,
+    }
+}
+
+fn verify_hmac<M: Mac + hmac::digest::KeyInit>(secret: &str, body: &[u8], digest: &[u8]) -> bool {
+    let Ok(mut mac) = <M as Mac>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(digest).is_ok()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SchedulerSettings, WebSubSettings};
+    use crate::feeds::tests::test_settings;
+    use wiremock::{
+        matchers::{body_string_contains, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_verify_signature() {
+        let body = b"<feed/>";
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let digest: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        assert!(verify_signature(
+            "secret",
+            &format!("sha256={}", digest),
+            body
+        ));
+        assert!(!verify_signature(
+            "other",
+            &format!("sha256={}", digest),
+            body
+        ));
+        assert!(!verify_signature(
+            "secret",
+            &format!("sha256={}", digest),
+            b"<x/>"
+        ));
+        assert!(!verify_signature(
+            "secret",
+            &format!("md5={}", digest),
+            body
+        ));
+        assert!(!verify_signature("secret", "sha256=zz", body));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_request_is_sent_to_the_hub() {
+        let hub = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("hub.mode=subscribe"))
+            .and(body_string_contains(
+                "hub.topic=https%3A%2F%2Fexample.com%2Ffeed",
+            ))
+            .and(body_string_contains(
+                "hub.callback=https%3A%2F%2Flaune.example.com%2Fapi%2Fwebsub%2F",
+            ))
+            .and(body_string_contains("hub.lease_seconds=864000"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&hub)
+            .await;
+
+        let fetcher = FeedFetcher::build(
+            test_settings(),
+            SchedulerSettings::default(),
+            WebSubSettings {
+                callback_base_url: "https://laune.example.com/".to_string(),
+                ..Default::default()
+            },
+        );
+        assert!(fetcher.websub_enabled());
+
+        let callback = fetcher.websub_callback(Uuid::new_v4());
+        fetcher
+            .send_subscription_request(
+                &hub.uri(),
+                "https://example.com/feed",
+                &callback,
+                Some("s3cret"),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn test_unsubscribe_clears_the_subscription_and_notifies_the_hub(pool: PgPool) {
+        let hub = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("hub.mode=unsubscribe"))
+            .and(body_string_contains(
+                "hub.topic=https%3A%2F%2Fexample.com%2Ffeed",
+            ))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&hub)
+            .await;
+
+        let fetcher = FeedFetcher::build(
+            test_settings(),
+            SchedulerSettings::default(),
+            WebSubSettings {
+                callback_base_url: "https://laune.example.com/".to_string(),
+                ..Default::default()
+            },
+        );
+        let feed_id: Uuid = sqlx::query(
+            r#"
+            INSERT INTO feeds (title, url, websub_hub, websub_topic, websub_secret)
+            VALUES ('Pushed', 'https://example.com/feed', $1, 'https://example.com/feed', 's3cret')
+            RETURNING id
+            "#,
+        )
+        .bind(hub.uri())
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+        .get("id");
+
+        fetcher.unsubscribe_websub(&pool, feed_id).await.unwrap();
+
+        let hub_url: Option<String> = sqlx::query("SELECT websub_hub FROM feeds WHERE id = $1")
+            .bind(feed_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .get("websub_hub");
+        assert_eq!(hub_url, None);
+
+        // The hub's verification of the unsubscribe is confirmed
+        let verification = WebSubVerification {
+            mode: "unsubscribe".to_string(),
+            topic: "https://example.com/feed".to_string(),
+            challenge: Some("abc".to_string()),
+            lease_seconds: None,
+            reason: None,
+        };
+        assert_eq!(
+            fetcher
+                .verify_websub(&pool, feed_id, &verification)
+                .await
+                .unwrap(),
+            Some("abc".to_string())
+        );
+
+        // Nothing is sent for a feed without a subscription
+        fetcher.unsubscribe_websub(&pool, feed_id).await.unwrap();
+    }
+
+    #[sqlx::test]
+    async fn test_pushed_content_is_decoded_with_its_charset(pool: PgPool) {
+        let fetcher = FeedFetcher::build(
+            test_settings(),
+            SchedulerSettings::default(),
+            WebSubSettings::default(),
+        );
+        let feed_id: Uuid = sqlx::query(
+            r#"
+            INSERT INTO feeds (title, url, websub_hub, websub_topic)
+            VALUES ('Pushed', 'https://example.com/feed', 'https://hub.example.com/', 'https://example.com/feed')
+            RETURNING id
+            "#,
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+        .get("id");
+
+        let mut body =
+            b"<?xml version=\"1.0\"?><rss version=\"2.0\"><channel><title>Pushed</title>\
+            <item><title>Caf"
+                .to_vec();
+        body.push(0xe9);
+        body.extend_from_slice(
+            b"</title><link>https://example.com/cafe</link></item></channel></rss>",
+        );
+
+        let count = fetcher
+            .receive_websub(
+                &pool,
+                feed_id,
+                None,
+                Some("application/rss+xml; charset=iso-8859-1"),
+                &body,
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let title: String = sqlx::query("SELECT title FROM articles WHERE feed_id = $1")
+            .bind(feed_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .get("title");
+        assert_eq!(title, "Café");
+    }
+
+    #[sqlx::test]
+    async fn test_granted_leases_are_clamped(pool: PgPool) {
+        let fetcher = FeedFetcher::build(
+            test_settings(),
+            SchedulerSettings::default(),
+            WebSubSettings::default(),
+        );
+        let feed_id: Uuid = sqlx::query(
+            r#"
+            INSERT INTO feeds (title, url, websub_hub, websub_topic)
+            VALUES ('Pushed', 'https://example.com/feed', 'https://hub.example.com/', 'https://example.com/feed')
+            RETURNING id
+            "#,
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+        .get("id");
+
+        for (granted, expected) in [
+            (100_000_000_000_000, MAX_LEASE_SECONDS),
+            (-5, MIN_LEASE_SECONDS),
+        ] {
+            let verification = WebSubVerification {
+                mode: "subscribe".to_string(),
+                topic: "https://example.com/feed".to_string(),
+                challenge: Some("abc".to_string()),
+                lease_seconds: Some(granted),
+                reason: None,
+            };
+            assert!(fetcher
+                .verify_websub(&pool, feed_id, &verification)
+                .await
+                .unwrap()
+                .is_some());
+
+            let expires_at: chrono::DateTime<Utc> =
+                sqlx::query("SELECT websub_lease_expires_at FROM feeds WHERE id = $1")
+                    .bind(feed_id)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+                    .get("websub_lease_expires_at");
+            let lease = expires_at - Utc::now();
+            assert!(lease <= Duration::seconds(expected));
+            assert!(lease > Duration::seconds(expected - 10));
+        }
+    }
+}
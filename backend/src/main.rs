@@ -4,11 +4,14 @@ use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod config;
+mod content;
 mod db;
 mod error;
 mod feeds;
 mod models;
+mod newsletters;
 mod routes;
+mod scheduler;
 mod summarizer;
 
 #[tokio::main]
@@ -36,6 +39,29 @@ async fn main() -> anyhow::Result<()> {
         return Err(anyhow::anyhow!("Database connection failed"));
     }
 
+    // One fetcher for the whole server, so its HTTP clients keep their connection pools
+    let fetcher = feeds::FeedFetcher::from_settings(&settings);
+
+    // Clean up articles stored before content was sanitized, then start the
+    // background tasks. Ingesting before that would compare new hashes with
+    // legacy ones and record every known entry as edited.
+    tokio::spawn({
+        let (pool, fetcher, settings) = (db_pool.clone(), fetcher.clone(), settings.clone());
+        async move {
+            content::run_normalization(&pool).await;
+
+            // Start refreshing feeds in the background
+            if settings.scheduler.enabled {
+                scheduler::spawn(pool.clone(), fetcher.clone(), settings.clone());
+            }
+
+            // Turn newsletters delivered to the local mailbox into articles
+            if !settings.newsletters.mailbox_path.trim().is_empty() {
+                newsletters::spawn(pool, fetcher, settings);
+            }
+        }
+    });
+
     // Build our application with routes
     let app = Router::new()
         .route("/health", get(health_check))
@@ -46,8 +72,11 @@ async fn main() -> anyhow::Result<()> {
                 .allow_methods(Any)
                 .allow_headers(Any),
         )
-        // Add the database connection pool to the application state
-        .with_state(db_pool);
+        // Add the database connection pool and the fetcher to the application state
+        .with_state(routes::AppState {
+            pool: db_pool,
+            fetcher,
+        });
 
     // Run the server
     let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
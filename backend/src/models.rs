@@ -1,32 +1,235 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{types::Json, FromRow};
+use std::{collections::BTreeMap, fmt};
 use uuid::Uuid;
 
 // Database models - these match the database schema directly
+
+/// Kind of source a feed reads its entries from
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum SourceType {
+    /// An RSS, Atom or JSON Feed document
+    #[default]
+    Rss,
+    /// Releases of a GitHub repository (`https://github.com/owner/repo`)
+    GithubReleases,
+    /// A Reddit listing (`https://www.reddit.com/r/rust`)
+    Reddit,
+    /// Public posts of a Mastodon account (`https://mastodon.social/@user`)
+    Mastodon,
+    /// A Hacker News search on Algolia (`https://hn.algolia.com/?query=rust`)
+    HackerNews,
+    /// A web page without a feed, read with CSS selectors
+    Scraped,
+    /// Email newsletters from one sender (`mailto:news@example.com`), delivered to the mailbox
+    Newsletter,
+    /// Pages listed in a sitemap or Google News sitemap (`https://example.com/news-sitemap.xml`)
+    Sitemap,
+}
+
+/// CSS selectors that turn a web page into feed entries.
+///
+/// `item` matches one element per entry, the other selectors are applied inside it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScrapeSelectors {
+    pub item: String,
+    /// Defaults to the item's first heading
+    pub title: Option<String>,
+    /// Defaults to the item itself when it is a link, else its first link
+    pub link: Option<String>,
+    /// Read from `datetime`/`content` attributes or the element's text
+    pub date: Option<String>,
+    /// Defaults to the whole item
+    pub content: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct Feed {
     pub id: Uuid,
     pub title: String,
     pub url: String,
+    pub description: Option<String>,
+    pub site_url: Option<String>,
+    pub language: Option<String>,
+    pub icon_url: Option<String>,
     pub active: bool,
     pub last_fetched: Option<DateTime<Utc>>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Overrides the scheduler's default interval when set
+    pub refresh_interval_minutes: Option<i32>,
+    pub next_fetch_at: Option<DateTime<Utc>>,
+    pub ttl_minutes: Option<i32>,
+    pub skip_hours: Vec<i32>,
+    pub skip_days: Vec<String>,
+    /// Replace feed excerpts with the text extracted from each article's page
+    pub fetch_full_content: bool,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// Failed refreshes since the last successful one
+    pub consecutive_failures: i32,
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// Why the feed was deactivated automatically
+    pub disabled_reason: Option<String>,
+    /// URLs the feed was permanently redirected away from, oldest first
+    pub previous_urls: Vec<String>,
+    /// WebSub hub the feed is subscribed to for push updates
+    pub websub_hub: Option<String>,
+    pub websub_lease_expires_at: Option<DateTime<Utc>>,
+    /// How the feed's entries are fetched
+    pub source_type: SourceType,
+    /// Where to find entries on the page of a `scraped` feed
+    pub scrape_selectors: Option<Json<ScrapeSelectors>>,
+    /// Encrypted `FeedHttpSettings`, never sent to clients
+    #[serde(skip)]
+    pub http_settings: Option<Vec<u8>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+// How the requests of a single feed are made
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FeedHttpSettings {
+    pub auth: Option<HttpAuth>,
+    /// Extra request headers by name
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Cookies by name, sent in a `Cookie` header
+    #[serde(default)]
+    pub cookies: BTreeMap<String, String>,
+    /// Replaces the default `Laune RSS Reader/1.0`
+    pub user_agent: Option<String>,
+    /// Replaces the fetcher's request timeout
+    pub timeout_seconds: Option<u64>,
+    /// HTTP(S) proxy URL, optionally with credentials
+    pub proxy: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpAuth {
+    Basic { username: String, password: String },
+    Bearer { token: String },
+}
+
+/// Stands in for secrets in debug output, which ends up in logs
+const REDACTED: &str = "<redacted>";
+
+impl fmt::Debug for FeedHttpSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |values: &BTreeMap<String, String>| {
+            values
+                .keys()
+                .map(|name| (name.clone(), REDACTED))
+                .collect::<BTreeMap<_, _>>()
+        };
+        f.debug_struct("FeedHttpSettings")
+            .field("auth", &self.auth)
+            .field("headers", &redacted(&self.headers))
+            .field("cookies", &redacted(&self.cookies))
+            .field("user_agent", &self.user_agent)
+            .field("timeout_seconds", &self.timeout_seconds)
+            .field("proxy", &self.proxy.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
+impl fmt::Debug for HttpAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpAuth::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &REDACTED)
+                .finish(),
+            HttpAuth::Bearer { .. } => f.debug_struct("Bearer").field("token", &REDACTED).finish(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct Article {
     pub id: Uuid,
     pub title: String,
     pub url: String,
+    /// Entry ID from the feed, unique within the feed
+    pub guid: String,
     pub feed_id: Uuid,
+    /// Sanitized HTML
     pub content: String,
+    /// Plain-text rendering of `content` for summaries and search
+    pub content_text: Option<String>,
+    /// Content provided by the feed, kept when the full text was extracted
+    pub excerpt: Option<String>,
     pub published_at: DateTime<Utc>,
+    /// When the source last reported a change to the entry
+    pub source_updated_at: Option<DateTime<Utc>>,
+    /// When the article was first ingested
+    pub first_seen_at: DateTime<Utc>,
+    /// How `published_at` was derived
+    pub date_source: DateSource,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// Where the `published_at` of an article comes from.
+///
+/// Undated and future-dated articles of a feed's first import or of a backfill of its
+/// history (`articles.first_import`) are left out of date-filtered listings and
+/// summaries: they are listed under the time of that import, which says nothing about
+/// their age. The ones found by later refreshes are kept, they were published around
+/// the time they were first seen.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum DateSource {
+    /// The entry's publication date
+    Published,
+    /// The entry's last update, it had no publication date
+    Updated,
+    /// The entry had no date, `published_at` is when it was first seen
+    Missing,
+    /// The entry was dated in the future, `published_at` is when it was first seen
+    ClampedFromFuture,
+}
+
+// Lets ingestion bind a whole batch of them as a `text[]`
+impl sqlx::postgres::PgHasArrayType for DateSource {
+    fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+        <String as sqlx::postgres::PgHasArrayType>::array_type_info()
+    }
+}
+
+// A previous version of an article, kept when the source edits the entry
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct ArticleRevision {
+    pub id: Uuid,
+    pub article_id: Uuid,
+    pub title: String,
+    pub url: String,
+    pub content: String,
+    pub published_at: DateTime<Utc>,
+    pub source_updated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Media file attached to an article, such as a podcast episode or video
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct ArticleEnclosure {
+    pub id: Uuid,
+    pub article_id: Uuid,
+    pub url: String,
+    pub mime_type: Option<String>,
+    /// Size in bytes
+    pub length: Option<i64>,
+    pub duration_seconds: Option<i32>,
+    pub thumbnail_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct Summary {
     pub id: Uuid,
@@ -40,14 +243,146 @@ pub struct Summary {
 // DTO models - these are used for API requests
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CreateFeedDto {
-    pub title: String,
+    /// Taken from the feed document when omitted
+    pub title: Option<String>,
     pub url: String,
+    pub refresh_interval_minutes: Option<i32>,
+    pub fetch_full_content: Option<bool>,
+    /// Defaults to `rss`, other sources take the page URL of what to follow
+    pub source_type: Option<SourceType>,
+    /// Required for `scraped` feeds
+    pub scrape_selectors: Option<ScrapeSelectors>,
+    /// Credentials and request options for private feeds
+    pub http_settings: Option<FeedHttpSettings>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UpdateFeedDto {
     pub title: Option<String>,
     pub url: Option<String>,
+    pub refresh_interval_minutes: Option<i32>,
+    pub fetch_full_content: Option<bool>,
+    pub scrape_selectors: Option<ScrapeSelectors>,
+    /// Replaces the stored settings, an empty object removes them
+    pub http_settings: Option<FeedHttpSettings>,
+}
+
+// Article together with its media enclosures, authors and categories
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArticleDetails {
+    #[serde(flatten)]
+    pub article: Article,
+    pub enclosures: Vec<ArticleEnclosure>,
+    pub authors: Vec<String>,
+    pub categories: Vec<String>,
+}
+
+// Revision of an article together with what changed in the next version
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArticleRevisionDiff {
+    #[serde(flatten)]
+    pub revision: ArticleRevision,
+    /// Unified diff from this revision to the version that replaced it
+    pub diff: String,
+}
+
+// DTOs for walking a feed's history
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BackfillFeedRequest {
+    /// Defaults to, and is capped at, `fetcher.max_backfill_pages`
+    pub max_pages: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackfillFeedResponse {
+    pub feed_id: Uuid,
+    pub pages_fetched: usize,
+    pub articles_added: usize,
+    pub article_ids: Vec<Uuid>,
+}
+
+// A stored feed document, listed without its body
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedPayload {
+    pub id: Uuid,
+    pub feed_id: Uuid,
+    pub url: String,
+    pub status: i32,
+    pub headers: serde_json::Value,
+    /// Size of the document before compression
+    pub size_bytes: i32,
+    pub fetched_at: DateTime<Utc>,
+}
+
+// DTOs for feed autodiscovery
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiscoverFeedsRequest {
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FeedCandidate {
+    pub url: String,
+    pub title: Option<String>,
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiscoverFeedsResponse {
+    pub candidates: Vec<FeedCandidate>,
+}
+
+// DTOs for trying out scrape selectors before creating a feed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScrapePreviewRequest {
+    pub url: String,
+    pub selectors: ScrapeSelectors,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScrapePreviewResponse {
+    /// Title of the page, used for the feed when none is given
+    pub title: Option<String>,
+    pub items: Vec<ScrapedItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScrapedItem {
+    pub title: String,
+    pub url: String,
+    pub published_at: Option<DateTime<Utc>>,
+    pub content: String,
+}
+
+// A feed whose recent refreshes failed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedHealth {
+    pub feed_id: Uuid,
+    pub title: String,
+    pub url: String,
+    pub active: bool,
+    pub consecutive_failures: i32,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub disabled_reason: Option<String>,
+    pub next_fetch_at: Option<DateTime<Utc>>,
+}
+
+// Query parameters a WebSub hub sends to verify a (un)subscription
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebSubVerification {
+    #[serde(rename = "hub.mode")]
+    pub mode: String,
+    #[serde(rename = "hub.topic")]
+    pub topic: String,
+    #[serde(rename = "hub.challenge")]
+    pub challenge: Option<String>,
+    #[serde(rename = "hub.lease_seconds")]
+    pub lease_seconds: Option<i64>,
+    /// Why a hub denied the subscription
+    #[serde(rename = "hub.reason")]
+    pub reason: Option<String>,
 }
 
 // DTO for toggling feed active status
@@ -71,11 +406,15 @@ pub struct BulkFetchRequest {
     pub end_date: Option<DateTime<Utc>>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// Only articles by this author (case-insensitive)
+    pub author: Option<String>,
+    /// Only articles in this category (case-insensitive)
+    pub category: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BulkFetchResponse {
-    pub articles: Vec<Article>,
+    pub articles: Vec<ArticleDetails>,
     pub total_count: i64,
     pub feed_summaries: Vec<FeedSummary>,
 }
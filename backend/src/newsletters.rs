@@ -0,0 +1,182 @@
+use crate::{config::Settings, db::DbPool, error::AppError, feeds::FeedFetcher};
+use mail_parser::mailbox::mbox::MessageIterator;
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tracing::{error, info, warn};
+
+/// Start delivering newsletters from the configured mailbox in the background
+pub fn spawn(
+    pool: DbPool,
+    fetcher: FeedFetcher,
+    settings: Settings,
+) -> tokio::task::JoinHandle<()> {
+    let path = PathBuf::from(&settings.newsletters.mailbox_path);
+    info!("Watching {} for newsletters", path.display());
+
+    tokio::spawn(async move {
+        let mut mailbox = Mailbox::new(path);
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(
+            settings.newsletters.poll_interval_seconds,
+        ));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            match mailbox.deliver(&pool, &fetcher).await {
+                Ok(0) => {}
+                Ok(count) => info!("Stored {} newsletter articles", count),
+                Err(e) => error!("Newsletter delivery failed: {}", e),
+            }
+        }
+    })
+}
+
+/// A Maildir directory or an mbox file
+struct Mailbox {
+    path: PathBuf,
+    /// Modification time of the mbox file when it was last read
+    mbox_read_at: Option<SystemTime>,
+}
+
+impl Mailbox {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            mbox_read_at: None,
+        }
+    }
+
+    /// Ingest messages that arrived since the last delivery
+    async fn deliver(&mut self, pool: &DbPool, fetcher: &FeedFetcher) -> Result<usize, AppError> {
+        if self.path.join("new").is_dir() {
+            deliver_maildir(&self.path, pool, fetcher).await
+        } else {
+            self.deliver_mbox(pool, fetcher).await
+        }
+    }
+
+    /// Re-read the mbox file when it changed, messages handled before are skipped
+    async fn deliver_mbox(
+        &mut self,
+        pool: &DbPool,
+        fetcher: &FeedFetcher,
+    ) -> Result<usize, AppError> {
+        let modified = tokio::fs::metadata(&self.path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| mailbox_error(&self.path, e))?;
+        if self.mbox_read_at == Some(modified) {
+            return Ok(0);
+        }
+
+        let contents = tokio::fs::read(&self.path)
+            .await
+            .map_err(|e| mailbox_error(&self.path, e))?;
+
+        let mut count = 0;
+        for message in MessageIterator::new(Cursor::new(contents)) {
+            let Ok(message) = message else {
+                warn!("Skipping unreadable message in {}", self.path.display());
+                continue;
+            };
+            match fetcher.ingest_newsletter(pool, message.contents()).await {
+                Ok(saved) => count += saved,
+                Err(e @ AppError::DatabaseError(_)) => return Err(e),
+                Err(e) => warn!("Skipping message in {}: {}", self.path.display(), e),
+            }
+        }
+
+        self.mbox_read_at = Some(modified);
+        Ok(count)
+    }
+}
+
+/// Ingest the messages in `new/` and move them to `cur/` marked as seen
+async fn deliver_maildir(
+    path: &Path,
+    pool: &DbPool,
+    fetcher: &FeedFetcher,
+) -> Result<usize, AppError> {
+    let mut entries = tokio::fs::read_dir(path.join("new"))
+        .await
+        .map_err(|e| mailbox_error(path, e))?;
+
+    let mut count = 0;
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| mailbox_error(path, e))?
+    {
+        let file = entry.path();
+        let raw = tokio::fs::read(&file)
+            .await
+            .map_err(|e| mailbox_error(&file, e))?;
+
+        // Database errors leave the message in new/ to be retried
+        match fetcher.ingest_newsletter(pool, &raw).await {
+            Ok(saved) => count += saved,
+            Err(e @ AppError::DatabaseError(_)) => return Err(e),
+            Err(e) => warn!("Skipping message {}: {}", file.display(), e),
+        }
+
+        let seen = path
+            .join("cur")
+            .join(format!("{}:2,S", entry.file_name().to_string_lossy()));
+        tokio::fs::rename(&file, &seen)
+            .await
+            .map_err(|e| mailbox_error(&file, e))?;
+    }
+
+    Ok(count)
+}
+
+fn mailbox_error(path: &Path, e: std::io::Error) -> AppError {
+    AppError::InternalServerError(format!("Failed to read mailbox {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FetcherSettings, SchedulerSettings, WebSubSettings};
+    use uuid::Uuid;
+
+    #[sqlx::test]
+    async fn test_maildir_messages_are_moved_to_cur(pool: DbPool) {
+        let fetcher = FeedFetcher::build(
+            FetcherSettings::default(),
+            SchedulerSettings::default(),
+            WebSubSettings::default(),
+        );
+        let maildir = std::env::temp_dir().join(format!("laune-maildir-{}", Uuid::new_v4()));
+        for folder in ["new", "cur", "tmp"] {
+            std::fs::create_dir_all(maildir.join(folder)).unwrap();
+        }
+        std::fs::write(
+            maildir.join("new").join("1700000000.1.host"),
+            concat!(
+                "From: digest@news.example\r\n",
+                "To: me@example.com\r\n",
+                "Subject: Issue\r\n",
+                "Message-ID: <1@news.example>\r\n",
+                "\r\n",
+                "This week in Rust\r\n",
+            ),
+        )
+        .unwrap();
+
+        let count = Mailbox::new(maildir.clone())
+            .deliver(&pool, &fetcher)
+            .await
+            .unwrap();
+        let new = std::fs::read_dir(maildir.join("new")).unwrap().count();
+        let seen = maildir.join("cur").join("1700000000.1.host:2,S").is_file();
+        std::fs::remove_dir_all(&maildir).unwrap();
+
+        assert_eq!(count, 1);
+        assert_eq!(new, 0);
+        assert!(seen);
+    }
+}
@@ -1,13 +1,34 @@
-use crate::db::DbPool;
-use axum::Router;
+use crate::{db::DbPool, feeds::FeedFetcher};
+use axum::{extract::FromRef, Router};
 
 pub mod articles;
 pub mod feeds;
 pub mod summaries;
+pub mod websub;
 
-pub fn api_router() -> Router<DbPool> {
+// State shared by all handlers, built once at startup
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: DbPool,
+    pub fetcher: FeedFetcher,
+}
+
+impl FromRef<AppState> for DbPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for FeedFetcher {
+    fn from_ref(state: &AppState) -> Self {
+        state.fetcher.clone()
+    }
+}
+
+pub fn api_router() -> Router<AppState> {
     Router::new()
         .merge(feeds::router())
         .merge(articles::router())
         .merge(summaries::router())
+        .merge(websub::router())
 }
@@ -1,7 +1,12 @@
 use crate::{
     db::DbPool,
     error::AppError,
-    models::{Article, BulkFetchRequest, BulkFetchResponse, FeedSummary},
+    feeds::FeedFetcher,
+    models::{
+        Article, ArticleDetails, ArticleEnclosure, ArticleRevision, ArticleRevisionDiff,
+        BulkFetchRequest, BulkFetchResponse, FeedSummary,
+    },
+    routes::AppState,
 };
 use axum::{
     extract::{Path, Query, State},
@@ -9,21 +14,27 @@ use axum::{
     Json, Router,
 };
 use serde::Deserialize;
+use similar::TextDiff;
 use sqlx::Row;
+use std::collections::HashMap;
 use tracing::{debug, error, info};
 use uuid::Uuid;
 
 #[derive(Deserialize)]
 pub struct ArticleQuery {
     feed_id: Option<Uuid>,
+    author: Option<String>,
+    category: Option<String>,
     limit: Option<i64>,
     offset: Option<i64>,
 }
 
-pub fn router() -> Router<DbPool> {
+pub fn router() -> Router<AppState> {
     Router::new()
         .route("/articles", get(list_articles))
         .route("/articles/:id", get(get_article))
+        .route("/articles/:id/revisions", get(get_article_revisions))
+        .route("/articles/:id/extract", post(extract_article))
         .route("/articles/bulk-fetch", post(bulk_fetch_articles))
         .route("/feeds/:feed_id/articles", get(get_feed_articles))
 }
@@ -35,55 +46,41 @@ async fn list_articles(
     let limit = query.limit.unwrap_or(20);
     let offset = query.offset.unwrap_or(0);
 
-    let articles = if let Some(feed_id) = query.feed_id {
-        // With feed_id filter
-        sqlx::query(
-            r#"
-            SELECT * FROM articles
-            WHERE feed_id = $1
-            ORDER BY published_at DESC
-            LIMIT $2 OFFSET $3
-            "#,
-        )
-        .bind(feed_id)
-        .bind(limit)
-        .bind(offset)
-        .map(|row: sqlx::postgres::PgRow| Article {
-            id: row.get("id"),
-            title: row.get("title"),
-            url: row.get("url"),
-            feed_id: row.get("feed_id"),
-            content: row.get("content"),
-            published_at: row.get("published_at"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        })
-        .fetch_all(&pool)
-        .await
-    } else {
-        // Without feed_id filter
-        sqlx::query(
-            r#"
-            SELECT * FROM articles
-            ORDER BY published_at DESC
-            LIMIT $1 OFFSET $2
-            "#,
-        )
-        .bind(limit)
-        .bind(offset)
-        .map(|row: sqlx::postgres::PgRow| Article {
-            id: row.get("id"),
-            title: row.get("title"),
-            url: row.get("url"),
-            feed_id: row.get("feed_id"),
-            content: row.get("content"),
-            published_at: row.get("published_at"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        })
-        .fetch_all(&pool)
-        .await
-    }
+    let articles = sqlx::query(&format!(
+        r#"
+        SELECT * FROM articles
+        WHERE ($1::UUID IS NULL OR feed_id = $1)
+        AND ($2::TEXT IS NULL OR {})
+        AND ($3::TEXT IS NULL OR {})
+        ORDER BY published_at DESC
+        LIMIT $4 OFFSET $5
+        "#,
+        author_filter("articles.id", "$2"),
+        category_filter("articles.id", "$3"),
+    ))
+    .bind(query.feed_id)
+    .bind(&query.author)
+    .bind(&query.category)
+    .bind(limit)
+    .bind(offset)
+    .map(|row: sqlx::postgres::PgRow| Article {
+        id: row.get("id"),
+        title: row.get("title"),
+        url: row.get("url"),
+        guid: row.get("guid"),
+        feed_id: row.get("feed_id"),
+        content: row.get("content"),
+        content_text: row.get("content_text"),
+        excerpt: row.get("excerpt"),
+        published_at: row.get("published_at"),
+        source_updated_at: row.get("source_updated_at"),
+        first_seen_at: row.get("first_seen_at"),
+        date_source: row.get("date_source"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    })
+    .fetch_all(&pool)
+    .await
     .map_err(|e| {
         error!("Failed to fetch articles: {:?}", e);
         AppError::DatabaseError(e.to_string())
@@ -93,19 +90,51 @@ async fn list_articles(
     Ok(Json(articles))
 }
 
+// SQL condition matching the article `article_id` by the author bound to `param`
+fn author_filter(article_id: &str, param: &str) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM article_authors aa JOIN authors au ON au.id = aa.author_id \
+         WHERE aa.article_id = {} AND LOWER(au.name) = LOWER({}))",
+        article_id, param
+    )
+}
+
+// SQL condition leaving out articles whose date is unknown, see `DateSource`
+pub(crate) fn has_known_date(article: &str) -> String {
+    format!(
+        "NOT ({0}.first_import AND {0}.date_source IN ('missing', 'clamped_from_future'))",
+        article
+    )
+}
+
+// SQL condition matching the article `article_id` by the category bound to `param`
+fn category_filter(article_id: &str, param: &str) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM article_categories ac JOIN categories c ON c.id = ac.category_id \
+         WHERE ac.article_id = {} AND LOWER(c.name) = LOWER({}))",
+        article_id, param
+    )
+}
+
 async fn get_article(
     State(pool): State<DbPool>,
     Path(id): Path<Uuid>,
-) -> Result<Json<Article>, AppError> {
+) -> Result<Json<ArticleDetails>, AppError> {
     let article = sqlx::query("SELECT * FROM articles WHERE id = $1")
         .bind(id)
         .map(|row: sqlx::postgres::PgRow| Article {
             id: row.get("id"),
             title: row.get("title"),
             url: row.get("url"),
+            guid: row.get("guid"),
             feed_id: row.get("feed_id"),
             content: row.get("content"),
+            content_text: row.get("content_text"),
+            excerpt: row.get("excerpt"),
             published_at: row.get("published_at"),
+            source_updated_at: row.get("source_updated_at"),
+            first_seen_at: row.get("first_seen_at"),
+            date_source: row.get("date_source"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         })
@@ -117,9 +146,186 @@ async fn get_article(
         })?
         .ok_or_else(|| AppError::NotFound(format!("Article with ID {} not found", id)))?;
 
+    let article = with_details(&pool, vec![article]).await?.remove(0);
+
     Ok(Json(article))
 }
 
+// Load the enclosures, authors and categories of several articles at once,
+// keeping the articles' order
+async fn with_details(
+    pool: &DbPool,
+    articles: Vec<Article>,
+) -> Result<Vec<ArticleDetails>, AppError> {
+    let ids: Vec<Uuid> = articles.iter().map(|article| article.id).collect();
+    let enclosures = sqlx::query(
+        "SELECT * FROM article_enclosures WHERE article_id = ANY($1) ORDER BY created_at, url",
+    )
+    .bind(&ids)
+    .map(|row: sqlx::postgres::PgRow| ArticleEnclosure {
+        id: row.get("id"),
+        article_id: row.get("article_id"),
+        url: row.get("url"),
+        mime_type: row.get("mime_type"),
+        length: row.get("length"),
+        duration_seconds: row.get("duration_seconds"),
+        thumbnail_url: row.get("thumbnail_url"),
+        created_at: row.get("created_at"),
+    })
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch article enclosures: {:?}", e);
+        AppError::DatabaseError(e.to_string())
+    })?;
+
+    let authors = sqlx::query(
+        r#"
+        SELECT aa.article_id, au.name FROM article_authors aa
+        JOIN authors au ON au.id = aa.author_id
+        WHERE aa.article_id = ANY($1)
+        ORDER BY au.name
+        "#,
+    )
+    .bind(&ids)
+    .map(|row: sqlx::postgres::PgRow| (row.get::<Uuid, _>("article_id"), row.get("name")))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch article authors: {:?}", e);
+        AppError::DatabaseError(e.to_string())
+    })?;
+
+    let categories = sqlx::query(
+        r#"
+        SELECT ac.article_id, c.name FROM article_categories ac
+        JOIN categories c ON c.id = ac.category_id
+        WHERE ac.article_id = ANY($1)
+        ORDER BY c.name
+        "#,
+    )
+    .bind(&ids)
+    .map(|row: sqlx::postgres::PgRow| (row.get::<Uuid, _>("article_id"), row.get("name")))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch article categories: {:?}", e);
+        AppError::DatabaseError(e.to_string())
+    })?;
+
+    let mut enclosures_by_article: HashMap<Uuid, Vec<ArticleEnclosure>> = HashMap::new();
+    for enclosure in enclosures {
+        enclosures_by_article
+            .entry(enclosure.article_id)
+            .or_default()
+            .push(enclosure);
+    }
+    let authors_by_article = group_names(authors);
+    let categories_by_article = group_names(categories);
+
+    Ok(articles
+        .into_iter()
+        .map(|article| ArticleDetails {
+            enclosures: enclosures_by_article
+                .remove(&article.id)
+                .unwrap_or_default(),
+            authors: authors_by_article
+                .get(&article.id)
+                .cloned()
+                .unwrap_or_default(),
+            categories: categories_by_article
+                .get(&article.id)
+                .cloned()
+                .unwrap_or_default(),
+            article,
+        })
+        .collect())
+}
+
+fn group_names(rows: Vec<(Uuid, String)>) -> HashMap<Uuid, Vec<String>> {
+    let mut grouped: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (article_id, name) in rows {
+        grouped.entry(article_id).or_default().push(name);
+    }
+    grouped
+}
+
+async fn get_article_revisions(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ArticleRevisionDiff>>, AppError> {
+    let current = sqlx::query("SELECT title, content FROM articles WHERE id = $1")
+        .bind(id)
+        .map(|row: sqlx::postgres::PgRow| {
+            (
+                row.get::<String, _>("title"),
+                row.get::<String, _>("content"),
+            )
+        })
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound(format!("Article with ID {} not found", id)))?;
+
+    let revisions = sqlx::query(
+        r#"
+        SELECT * FROM article_revisions
+        WHERE article_id = $1
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(id)
+    .map(|row: sqlx::postgres::PgRow| ArticleRevision {
+        id: row.get("id"),
+        article_id: row.get("article_id"),
+        title: row.get("title"),
+        url: row.get("url"),
+        content: row.get("content"),
+        published_at: row.get("published_at"),
+        source_updated_at: row.get("source_updated_at"),
+        created_at: row.get("created_at"),
+    })
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch revisions for article {}: {:?}", id, e);
+        AppError::DatabaseError(e.to_string())
+    })?;
+
+    // Newest first, so each revision was replaced by the one before it in the list
+    let mut newer = render_revision(&current.0, &current.1);
+    let revisions = revisions
+        .into_iter()
+        .map(|revision| {
+            let older = render_revision(&revision.title, &revision.content);
+            let diff = TextDiff::from_lines(&older, &newer)
+                .unified_diff()
+                .header("previous", "next")
+                .to_string();
+            newer = older;
+            ArticleRevisionDiff { revision, diff }
+        })
+        .collect();
+
+    Ok(Json(revisions))
+}
+
+// Replace an article's content with the full text from its web page
+async fn extract_article(
+    State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Article>, AppError> {
+    let article = fetcher.extract_full_content(&pool, id).await?;
+
+    Ok(Json(article))
+}
+
+// Text that revision diffs are computed on
+fn render_revision(title: &str, content: &str) -> String {
+    format!("{}\n\n{}\n", title, content)
+}
+
 async fn get_feed_articles(
     State(pool): State<DbPool>,
     Path(feed_id): Path<Uuid>,
@@ -143,24 +349,36 @@ async fn get_feed_articles(
     let limit = query.limit.unwrap_or(20);
     let offset = query.offset.unwrap_or(0);
 
-    let articles = sqlx::query(
+    let articles = sqlx::query(&format!(
         r#"
         SELECT * FROM articles
         WHERE feed_id = $1
+        AND ($2::TEXT IS NULL OR {})
+        AND ($3::TEXT IS NULL OR {})
         ORDER BY published_at DESC
-        LIMIT $2 OFFSET $3
+        LIMIT $4 OFFSET $5
         "#,
-    )
+        author_filter("articles.id", "$2"),
+        category_filter("articles.id", "$3"),
+    ))
     .bind(feed_id)
+    .bind(&query.author)
+    .bind(&query.category)
     .bind(limit)
     .bind(offset)
     .map(|row: sqlx::postgres::PgRow| Article {
         id: row.get("id"),
         title: row.get("title"),
         url: row.get("url"),
+        guid: row.get("guid"),
         feed_id: row.get("feed_id"),
         content: row.get("content"),
+        content_text: row.get("content_text"),
+        excerpt: row.get("excerpt"),
         published_at: row.get("published_at"),
+        source_updated_at: row.get("source_updated_at"),
+        first_seen_at: row.get("first_seen_at"),
+        date_source: row.get("date_source"),
         created_at: row.get("created_at"),
         updated_at: row.get("updated_at"),
     })
@@ -177,18 +395,19 @@ async fn get_feed_articles(
 
 async fn bulk_fetch_articles(
     State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
     Json(request): Json<BulkFetchRequest>,
 ) -> Result<Json<BulkFetchResponse>, AppError> {
     info!("Starting bulk fetch for {} feeds", request.feed_ids.len());
 
     // First, fetch new articles online for all selected feeds
     if !request.feed_ids.is_empty() {
-        let fetcher = crate::feeds::FeedFetcher::new();
         let mut total_new_articles = 0;
 
         for feed_id in &request.feed_ids {
             match fetcher.refresh_feed(&pool, *feed_id).await {
-                Ok(count) => {
+                Ok(saved) => {
+                    let count = saved.inserted.len();
                     total_new_articles += count;
                     info!("Fetched {} new articles from feed {}", count, feed_id);
                 }
@@ -229,6 +448,21 @@ async fn bulk_fetch_articles(
         bind_index += 1;
     }
 
+    if request.start_date.is_some() || request.end_date.is_some() {
+        conditions.push(has_known_date("articles"));
+    }
+
+    // Add author and category filters
+    if request.author.is_some() {
+        conditions.push(author_filter("articles.id", &format!("${}", bind_index)));
+        bind_index += 1;
+    }
+
+    if request.category.is_some() {
+        conditions.push(category_filter("articles.id", &format!("${}", bind_index)));
+        bind_index += 1;
+    }
+
     // Build the complete query
     let where_clause = if !conditions.is_empty() {
         format!(" WHERE {}", conditions.join(" AND "))
@@ -262,6 +496,14 @@ async fn bulk_fetch_articles(
         query = query.bind(end_date);
     }
 
+    if let Some(author) = &request.author {
+        query = query.bind(author);
+    }
+
+    if let Some(category) = &request.category {
+        query = query.bind(category);
+    }
+
     // Bind limit and offset
     query = query.bind(limit).bind(offset);
 
@@ -270,9 +512,15 @@ async fn bulk_fetch_articles(
             id: row.get("id"),
             title: row.get("title"),
             url: row.get("url"),
+            guid: row.get("guid"),
             feed_id: row.get("feed_id"),
             content: row.get("content"),
+            content_text: row.get("content_text"),
+            excerpt: row.get("excerpt"),
             published_at: row.get("published_at"),
+            source_updated_at: row.get("source_updated_at"),
+            first_seen_at: row.get("first_seen_at"),
+            date_source: row.get("date_source"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         })
@@ -283,6 +531,8 @@ async fn bulk_fetch_articles(
             AppError::DatabaseError(e.to_string())
         })?;
 
+    let articles = with_details(&pool, articles).await?;
+
     // Get total count for pagination
     let count_query = format!("SELECT COUNT(*) as total FROM articles{}", where_clause);
 
@@ -301,6 +551,14 @@ async fn bulk_fetch_articles(
         count_sql = count_sql.bind(end_date);
     }
 
+    if let Some(author) = &request.author {
+        count_sql = count_sql.bind(author);
+    }
+
+    if let Some(category) = &request.category {
+        count_sql = count_sql.bind(category);
+    }
+
     let total_count: i64 = count_sql
         .fetch_one(&pool)
         .await
@@ -337,6 +595,26 @@ async fn bulk_fetch_articles(
                 "(a.published_at <= ${} OR a.id IS NULL)",
                 summary_bind_index
             ));
+            summary_bind_index += 1;
+        }
+
+        if request.start_date.is_some() || request.end_date.is_some() {
+            summary_conditions.push(format!("({} OR a.id IS NULL)", has_known_date("a")));
+        }
+
+        if request.author.is_some() {
+            summary_conditions.push(format!(
+                "({} OR a.id IS NULL)",
+                author_filter("a.id", &format!("${}", summary_bind_index))
+            ));
+            summary_bind_index += 1;
+        }
+
+        if request.category.is_some() {
+            summary_conditions.push(format!(
+                "({} OR a.id IS NULL)",
+                category_filter("a.id", &format!("${}", summary_bind_index))
+            ));
         }
 
         let summary_query = format!(
@@ -365,6 +643,14 @@ async fn bulk_fetch_articles(
             summary_sql = summary_sql.bind(end_date);
         }
 
+        if let Some(author) = &request.author {
+            summary_sql = summary_sql.bind(author);
+        }
+
+        if let Some(category) = &request.category {
+            summary_sql = summary_sql.bind(category);
+        }
+
         summary_sql
             .map(|row: sqlx::postgres::PgRow| FeedSummary {
                 feed_id: row.get("feed_id"),
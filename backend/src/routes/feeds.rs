@@ -1,23 +1,27 @@
 use crate::{
     db::DbPool,
     error::AppError,
-    feeds::FeedFetcher,
+    feeds::{FeedFetcher, FeedMetadata},
     models::{
-        CreateFeedDto, Feed, ToggleFeedStatusRequest, ToggleFeedStatusResponse, UpdateFeedDto,
+        BackfillFeedRequest, BackfillFeedResponse, CreateFeedDto, DiscoverFeedsRequest,
+        DiscoverFeedsResponse, Feed, FeedHealth, FeedPayload, ScrapePreviewRequest,
+        ScrapePreviewResponse, SourceType, ToggleFeedStatusRequest, ToggleFeedStatusResponse,
+        UpdateFeedDto,
     },
+    routes::AppState,
 };
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, patch, post},
     Json, Router,
 };
-use sqlx::Row;
-use tracing::{error, info};
+use sqlx::{types::Json as SqlJson, Row};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
-pub fn router() -> Router<DbPool> {
+pub fn router() -> Router<AppState> {
     Router::new()
         .route("/feeds", get(list_feeds).post(create_feed))
         .route(
@@ -25,8 +29,18 @@ pub fn router() -> Router<DbPool> {
             get(get_feed).put(update_feed).delete(delete_feed),
         )
         .route("/feeds/:id/refresh", post(refresh_feed))
+        .route("/feeds/:id/backfill", post(backfill_feed))
+        .route("/feeds/:id/payloads", get(list_feed_payloads))
+        .route("/feeds/:id/payloads/:payload_id", get(get_feed_payload))
+        .route(
+            "/feeds/:id/payloads/:payload_id/reprocess",
+            post(reprocess_feed_payload),
+        )
         .route("/feeds/:id/toggle-status", patch(toggle_feed_status))
         .route("/feeds/refresh-all-active", post(refresh_all_active_feeds))
+        .route("/feeds/discover", post(discover_feeds))
+        .route("/feeds/scrape-preview", post(scrape_preview))
+        .route("/feeds/health", get(feeds_health))
 }
 
 // List all feeds
@@ -36,8 +50,31 @@ async fn list_feeds(State(pool): State<DbPool>) -> Result<Json<Vec<Feed>>, AppEr
             id: row.get("id"),
             title: row.get("title"),
             url: row.get("url"),
+            description: row.get("description"),
+            site_url: row.get("site_url"),
+            language: row.get("language"),
+            icon_url: row.get("icon_url"),
             active: row.get("active"),
             last_fetched: row.get("last_fetched"),
+            etag: row.get("etag"),
+            last_modified: row.get("last_modified"),
+            refresh_interval_minutes: row.get("refresh_interval_minutes"),
+            next_fetch_at: row.get("next_fetch_at"),
+            ttl_minutes: row.get("ttl_minutes"),
+            skip_hours: row.get("skip_hours"),
+            skip_days: row.get("skip_days"),
+            fetch_full_content: row.get("fetch_full_content"),
+            last_error: row.get("last_error"),
+            last_error_at: row.get("last_error_at"),
+            consecutive_failures: row.get("consecutive_failures"),
+            last_success_at: row.get("last_success_at"),
+            disabled_reason: row.get("disabled_reason"),
+            previous_urls: row.get("previous_urls"),
+            websub_hub: row.get("websub_hub"),
+            websub_lease_expires_at: row.get("websub_lease_expires_at"),
+            source_type: row.get("source_type"),
+            scrape_selectors: row.get("scrape_selectors"),
+            http_settings: row.get("http_settings"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         })
@@ -51,14 +88,76 @@ async fn list_feeds(State(pool): State<DbPool>) -> Result<Json<Vec<Feed>>, AppEr
     Ok(Json(feeds))
 }
 
-// Create a new feed
+// Create a new feed, resolving website URLs to the feed they advertise
 async fn create_feed(
     State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
     Json(payload): Json<CreateFeedDto>,
-) -> Result<(StatusCode, Json<Feed>), AppError> {
+) -> Result<Response, AppError> {
+    validate_refresh_interval(payload.refresh_interval_minutes)?;
+
+    let http_settings = match &payload.http_settings {
+        Some(settings) => fetcher.seal_http_settings(settings)?,
+        None => None,
+    };
+    // Private feeds are discovered and read with their own credentials
+    let feed_fetcher = fetcher.with_sealed_http_settings(http_settings.as_deref())?;
+
+    // Metadata of the document when discovery or the preview already downloaded it
+    let mut metadata = None;
+    let source_type = payload.source_type.unwrap_or_default();
+    let url = if source_type == SourceType::Rss {
+        let mut discovered = feed_fetcher.discover(&payload.url).await?;
+        match discovered.len() {
+            0 => {
+                return Err(AppError::FeedParsingError(format!(
+                    "No feed found at {}",
+                    payload.url
+                )))
+            }
+            1 => {
+                let found = discovered.remove(0);
+                metadata = found.metadata;
+                found.candidate.url
+            }
+            // Let the client pick one of the advertised feeds
+            _ => {
+                let candidates = discovered
+                    .into_iter()
+                    .map(|found| found.candidate)
+                    .collect();
+                return Ok((
+                    StatusCode::MULTIPLE_CHOICES,
+                    Json(DiscoverFeedsResponse { candidates }),
+                )
+                    .into_response());
+            }
+        }
+    } else {
+        // Other sources take the URL of the page to follow as is
+        payload.url.clone()
+    };
+
+    // A scraped feed is only useful if its selectors find something
+    if source_type == SourceType::Scraped {
+        let selectors = payload.scrape_selectors.as_ref().ok_or_else(|| {
+            AppError::ValidationError("Scraped feeds need scrape_selectors".to_string())
+        })?;
+        let (preview, page) = feed_fetcher
+            .preview_scrape_with_metadata(&url, selectors)
+            .await?;
+        if preview.items.is_empty() {
+            return Err(AppError::ValidationError(format!(
+                "The selectors match no items on {}",
+                url
+            )));
+        }
+        metadata = Some(page);
+    }
+
     // Check if feed with the same URL already exists
     let existing = sqlx::query("SELECT id FROM feeds WHERE url = $1")
-        .bind(&payload.url)
+        .bind(&url)
         .fetch_optional(&pool)
         .await
         .map_err(|e| AppError::DatabaseError(e.to_string()))?;
@@ -69,22 +168,69 @@ async fn create_feed(
         ));
     }
 
+    // Make sure the URL serves a feed and read its metadata
+    let metadata = match metadata {
+        Some(metadata) => metadata,
+        None => feed_fetcher.fetch_metadata(source_type, &url).await?,
+    };
+    let title = payload
+        .title
+        .filter(|title| !title.trim().is_empty())
+        .or(metadata.title)
+        .unwrap_or_else(|| url.clone());
+    let websub_topic = metadata.self_url.clone().unwrap_or_else(|| url.clone());
+    let websub_hub = metadata.hub_url.clone();
+
     // Create the feed
     let feed = sqlx::query(
         r#"
-        INSERT INTO feeds (title, url)
-        VALUES ($1, $2)
+        INSERT INTO feeds
+        (title, url, refresh_interval_minutes, fetch_full_content,
+         description, site_url, language, icon_url, source_type, scrape_selectors, http_settings)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
         RETURNING *
         "#,
     )
-    .bind(&payload.title)
-    .bind(&payload.url)
+    .bind(&title)
+    .bind(&url)
+    .bind(payload.refresh_interval_minutes)
+    .bind(payload.fetch_full_content.unwrap_or(false))
+    .bind(metadata.description)
+    .bind(metadata.site_url)
+    .bind(metadata.language)
+    .bind(metadata.icon_url)
+    .bind(source_type)
+    .bind(payload.scrape_selectors.map(SqlJson))
+    .bind(http_settings)
     .map(|row: sqlx::postgres::PgRow| Feed {
         id: row.get("id"),
         title: row.get("title"),
         url: row.get("url"),
+        description: row.get("description"),
+        site_url: row.get("site_url"),
+        language: row.get("language"),
+        icon_url: row.get("icon_url"),
         active: row.get("active"),
         last_fetched: row.get("last_fetched"),
+        etag: row.get("etag"),
+        last_modified: row.get("last_modified"),
+        refresh_interval_minutes: row.get("refresh_interval_minutes"),
+        next_fetch_at: row.get("next_fetch_at"),
+        ttl_minutes: row.get("ttl_minutes"),
+        skip_hours: row.get("skip_hours"),
+        skip_days: row.get("skip_days"),
+        fetch_full_content: row.get("fetch_full_content"),
+        last_error: row.get("last_error"),
+        last_error_at: row.get("last_error_at"),
+        consecutive_failures: row.get("consecutive_failures"),
+        last_success_at: row.get("last_success_at"),
+        disabled_reason: row.get("disabled_reason"),
+        previous_urls: row.get("previous_urls"),
+        websub_hub: row.get("websub_hub"),
+        websub_lease_expires_at: row.get("websub_lease_expires_at"),
+        source_type: row.get("source_type"),
+        scrape_selectors: row.get("scrape_selectors"),
+        http_settings: row.get("http_settings"),
         created_at: row.get("created_at"),
         updated_at: row.get("updated_at"),
     })
@@ -96,7 +242,49 @@ async fn create_feed(
     })?;
 
     info!("Created new feed: {} ({})", feed.title, feed.id);
-    Ok((StatusCode::CREATED, Json(feed)))
+
+    // Ask the feed's hub to push updates, polling keeps working if it refuses
+    if let Some(hub) = websub_hub.filter(|_| fetcher.websub_enabled()) {
+        let feed_id = feed.id;
+        tokio::spawn(async move {
+            if let Err(e) = fetcher
+                .subscribe_websub(&pool, feed_id, &hub, &websub_topic)
+                .await
+            {
+                warn!("Failed to subscribe feed {} to WebSub hub: {}", feed_id, e);
+            }
+        });
+    }
+
+    Ok((StatusCode::CREATED, Json(feed)).into_response())
+}
+
+// List the feeds available at a website or feed URL
+async fn discover_feeds(
+    State(fetcher): State<FeedFetcher>,
+    Json(payload): Json<DiscoverFeedsRequest>,
+) -> Result<Json<DiscoverFeedsResponse>, AppError> {
+    let candidates = fetcher.discover_feeds(&payload.url).await?;
+
+    info!("Discovered {} feeds at {}", candidates.len(), payload.url);
+    Ok(Json(DiscoverFeedsResponse { candidates }))
+}
+
+// Show what scrape selectors extract from a page before creating a feed with them
+async fn scrape_preview(
+    State(fetcher): State<FeedFetcher>,
+    Json(payload): Json<ScrapePreviewRequest>,
+) -> Result<Json<ScrapePreviewResponse>, AppError> {
+    let preview = fetcher
+        .preview_scrape(&payload.url, &payload.selectors)
+        .await?;
+
+    info!(
+        "Scrape selectors found {} items at {}",
+        preview.items.len(),
+        payload.url
+    );
+    Ok(Json(preview))
 }
 
 // Get a specific feed by ID
@@ -110,8 +298,31 @@ async fn get_feed(
             id: row.get("id"),
             title: row.get("title"),
             url: row.get("url"),
+            description: row.get("description"),
+            site_url: row.get("site_url"),
+            language: row.get("language"),
+            icon_url: row.get("icon_url"),
             active: row.get("active"),
             last_fetched: row.get("last_fetched"),
+            etag: row.get("etag"),
+            last_modified: row.get("last_modified"),
+            refresh_interval_minutes: row.get("refresh_interval_minutes"),
+            next_fetch_at: row.get("next_fetch_at"),
+            ttl_minutes: row.get("ttl_minutes"),
+            skip_hours: row.get("skip_hours"),
+            skip_days: row.get("skip_days"),
+            fetch_full_content: row.get("fetch_full_content"),
+            last_error: row.get("last_error"),
+            last_error_at: row.get("last_error_at"),
+            consecutive_failures: row.get("consecutive_failures"),
+            last_success_at: row.get("last_success_at"),
+            disabled_reason: row.get("disabled_reason"),
+            previous_urls: row.get("previous_urls"),
+            websub_hub: row.get("websub_hub"),
+            websub_lease_expires_at: row.get("websub_lease_expires_at"),
+            source_type: row.get("source_type"),
+            scrape_selectors: row.get("scrape_selectors"),
+            http_settings: row.get("http_settings"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         })
@@ -129,22 +340,28 @@ async fn get_feed(
 // Update an existing feed
 async fn update_feed(
     State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateFeedDto>,
 ) -> Result<Json<Feed>, AppError> {
     // Check if feed exists
-    let feed_exists = sqlx::query("SELECT id FROM feeds WHERE id = $1")
+    let current = sqlx::query("SELECT source_type, http_settings FROM feeds WHERE id = $1")
         .bind(id)
         .fetch_optional(&pool)
         .await
         .map_err(|e| AppError::DatabaseError(e.to_string()))?
-        .is_some();
+        .ok_or_else(|| AppError::NotFound(format!("Feed with ID {} not found", id)))?;
+    let source_type: SourceType = current.get("source_type");
 
-    if !feed_exists {
-        return Err(AppError::NotFound(format!("Feed with ID {} not found", id)));
-    }
+    validate_refresh_interval(payload.refresh_interval_minutes)?;
+
+    let http_settings = match &payload.http_settings {
+        Some(settings) => fetcher.seal_http_settings(settings)?,
+        None => current.get("http_settings"),
+    };
 
     // If URL is provided, check if it's already used by another feed
+    let mut metadata = FeedMetadata::default();
     if let Some(url) = &payload.url {
         let existing = sqlx::query("SELECT id FROM feeds WHERE url = $1 AND id != $2")
             .bind(url)
@@ -158,29 +375,83 @@ async fn update_feed(
                 "Feed with this URL already exists".to_string(),
             ));
         }
+
+        // Reject URLs that do not serve a feed, the title is left to the client
+        metadata = fetcher
+            .with_sealed_http_settings(http_settings.as_deref())?
+            .fetch_metadata(source_type, url)
+            .await?;
+
+        // The hub would keep pushing the old URL's content
+        if let Err(e) = fetcher.unsubscribe_websub(&pool, id).await {
+            warn!("Failed to unsubscribe feed {} from WebSub hub: {}", id, e);
+        }
     }
+    let websub_hub = metadata.hub_url.clone();
+    let websub_topic = metadata.self_url.clone().or_else(|| payload.url.clone());
 
-    // Update the feed
+    // Update the feed, cache validators belong to the old URL
     let feed = sqlx::query(
         r#"
         UPDATE feeds
         SET
             title = COALESCE($1, title),
             url = COALESCE($2, url),
+            refresh_interval_minutes = COALESCE($3, refresh_interval_minutes),
+            description = COALESCE($4, description),
+            site_url = COALESCE($5, site_url),
+            language = COALESCE($6, language),
+            icon_url = COALESCE($7, icon_url),
+            fetch_full_content = COALESCE($8, fetch_full_content),
+            scrape_selectors = COALESCE($9, scrape_selectors),
+            http_settings = $10,
+            etag = CASE WHEN $2 IS NULL THEN etag END,
+            last_modified = CASE WHEN $2 IS NULL THEN last_modified END,
             updated_at = NOW()
-        WHERE id = $3
+        WHERE id = $11
         RETURNING *
         "#,
     )
     .bind(payload.title)
     .bind(payload.url)
+    .bind(payload.refresh_interval_minutes)
+    .bind(metadata.description)
+    .bind(metadata.site_url)
+    .bind(metadata.language)
+    .bind(metadata.icon_url)
+    .bind(payload.fetch_full_content)
+    .bind(payload.scrape_selectors.map(SqlJson))
+    .bind(http_settings)
     .bind(id)
     .map(|row: sqlx::postgres::PgRow| Feed {
         id: row.get("id"),
         title: row.get("title"),
         url: row.get("url"),
+        description: row.get("description"),
+        site_url: row.get("site_url"),
+        language: row.get("language"),
+        icon_url: row.get("icon_url"),
         active: row.get("active"),
         last_fetched: row.get("last_fetched"),
+        etag: row.get("etag"),
+        last_modified: row.get("last_modified"),
+        refresh_interval_minutes: row.get("refresh_interval_minutes"),
+        next_fetch_at: row.get("next_fetch_at"),
+        ttl_minutes: row.get("ttl_minutes"),
+        skip_hours: row.get("skip_hours"),
+        skip_days: row.get("skip_days"),
+        fetch_full_content: row.get("fetch_full_content"),
+        last_error: row.get("last_error"),
+        last_error_at: row.get("last_error_at"),
+        consecutive_failures: row.get("consecutive_failures"),
+        last_success_at: row.get("last_success_at"),
+        disabled_reason: row.get("disabled_reason"),
+        previous_urls: row.get("previous_urls"),
+        websub_hub: row.get("websub_hub"),
+        websub_lease_expires_at: row.get("websub_lease_expires_at"),
+        source_type: row.get("source_type"),
+        scrape_selectors: row.get("scrape_selectors"),
+        http_settings: row.get("http_settings"),
         created_at: row.get("created_at"),
         updated_at: row.get("updated_at"),
     })
@@ -192,12 +463,25 @@ async fn update_feed(
     })?;
 
     info!("Updated feed: {} ({})", feed.title, feed.id);
+
+    // Subscribe to the new URL's hub, as when the feed was created
+    if let (Some(hub), Some(topic)) = (websub_hub, websub_topic) {
+        if fetcher.websub_enabled() {
+            tokio::spawn(async move {
+                if let Err(e) = fetcher.subscribe_websub(&pool, id, &hub, &topic).await {
+                    warn!("Failed to subscribe feed {} to WebSub hub: {}", id, e);
+                }
+            });
+        }
+    }
+
     Ok(Json(feed))
 }
 
 // Delete a feed
 async fn delete_feed(
     State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
     Path(id): Path<Uuid>,
 ) -> Result<impl IntoResponse, AppError> {
     // Check if feed exists
@@ -212,6 +496,11 @@ async fn delete_feed(
         return Err(AppError::NotFound(format!("Feed with ID {} not found", id)));
     }
 
+    // Stop the hub from pushing to a callback that is about to disappear
+    if let Err(e) = fetcher.unsubscribe_websub(&pool, id).await {
+        warn!("Failed to unsubscribe feed {} from WebSub hub: {}", id, e);
+    }
+
     // Delete the feed
     sqlx::query("DELETE FROM feeds WHERE id = $1")
         .bind(id)
@@ -229,6 +518,7 @@ async fn delete_feed(
 // Refresh a feed by fetching new articles
 async fn refresh_feed(
     State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, AppError> {
     // Check if feed exists
@@ -243,20 +533,152 @@ async fn refresh_feed(
         return Err(AppError::NotFound(format!("Feed with ID {} not found", id)));
     }
 
-    // Create a feed fetcher
-    let fetcher = FeedFetcher::new();
-
     // Fetch and save articles
-    let count = fetcher.refresh_feed(&pool, id).await?;
+    let saved = fetcher.refresh_feed(&pool, id).await?;
+
+    info!(
+        "Refreshed feed {} - {} new articles",
+        id,
+        saved.inserted.len()
+    );
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "feed_id": id.to_string(),
+        "articles_added": saved.inserted.len(),
+        "articles_updated": saved.updated.len(),
+        "article_ids": saved.inserted
+    })))
+}
+
+// Store the older entries of a feed from its archives or earlier pages
+async fn backfill_feed(
+    State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
+    Path(id): Path<Uuid>,
+    payload: Option<Json<BackfillFeedRequest>>,
+) -> Result<Json<BackfillFeedResponse>, AppError> {
+    let Json(payload) = payload.unwrap_or_default();
+
+    let backfill = fetcher.backfill_feed(&pool, id, payload.max_pages).await?;
+
+    info!(
+        "Backfilled feed {} - {} new articles from {} pages",
+        id,
+        backfill.inserted.len(),
+        backfill.pages_fetched
+    );
+    Ok(Json(BackfillFeedResponse {
+        feed_id: id,
+        pages_fetched: backfill.pages_fetched,
+        articles_added: backfill.inserted.len(),
+        article_ids: backfill.inserted,
+    }))
+}
+
+// List the stored documents of a feed, newest first
+async fn list_feed_payloads(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<FeedPayload>>, AppError> {
+    let payloads = sqlx::query(
+        r#"
+        SELECT id, feed_id, url, status, headers, size_bytes, fetched_at
+        FROM feed_payloads
+        WHERE feed_id = $1
+        ORDER BY fetched_at DESC
+        "#,
+    )
+    .bind(id)
+    .map(|row: sqlx::postgres::PgRow| FeedPayload {
+        id: row.get("id"),
+        feed_id: row.get("feed_id"),
+        url: row.get("url"),
+        status: row.get("status"),
+        headers: row.get("headers"),
+        size_bytes: row.get("size_bytes"),
+        fetched_at: row.get("fetched_at"),
+    })
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch payloads of feed {}: {:?}", id, e);
+        AppError::DatabaseError(e.to_string())
+    })?;
+
+    Ok(Json(payloads))
+}
+
+// Download a stored document as it was served
+async fn get_feed_payload(
+    State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
+    Path((id, payload_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, AppError> {
+    let payload = fetcher.load_payload(&pool, id, payload_id).await?;
+
+    let content_type = payload
+        .content_type()
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    Ok(([(header::CONTENT_TYPE, content_type)], payload.body).into_response())
+}
 
-    info!("Refreshed feed {} - {} new articles", id, count);
+// Run ingestion again on a stored document
+async fn reprocess_feed_payload(
+    State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
+    Path((id, payload_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let saved = fetcher.reprocess_payload(&pool, id, payload_id).await?;
+
+    info!(
+        "Reprocessed payload {} of feed {} - {} new articles, {} updated",
+        payload_id,
+        id,
+        saved.inserted.len(),
+        saved.updated.len()
+    );
     Ok(Json(serde_json::json!({
         "success": true,
         "feed_id": id.to_string(),
-        "articles_added": count
+        "payload_id": payload_id.to_string(),
+        "articles_added": saved.inserted.len(),
+        "articles_updated": saved.updated.len(),
+        "article_ids": saved.inserted
     })))
 }
 
+// List feeds that are failing or were deactivated because of failures
+async fn feeds_health(State(pool): State<DbPool>) -> Result<Json<Vec<FeedHealth>>, AppError> {
+    let feeds = sqlx::query(
+        r#"
+        SELECT * FROM feeds
+        WHERE consecutive_failures > 0 OR disabled_reason IS NOT NULL
+        ORDER BY consecutive_failures DESC, title
+        "#,
+    )
+    .map(|row: sqlx::postgres::PgRow| FeedHealth {
+        feed_id: row.get("id"),
+        title: row.get("title"),
+        url: row.get("url"),
+        active: row.get("active"),
+        consecutive_failures: row.get("consecutive_failures"),
+        last_error: row.get("last_error"),
+        last_error_at: row.get("last_error_at"),
+        last_success_at: row.get("last_success_at"),
+        disabled_reason: row.get("disabled_reason"),
+        next_fetch_at: row.get("next_fetch_at"),
+    })
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to fetch feed health: {:?}", e);
+        AppError::DatabaseError(e.to_string())
+    })?;
+
+    Ok(Json(feeds))
+}
+
 // Toggle feed active/inactive status
 async fn toggle_feed_status(
     State(pool): State<DbPool>,
@@ -275,11 +697,15 @@ async fn toggle_feed_status(
         return Err(AppError::NotFound(format!("Feed with ID {} not found", id)));
     }
 
-    // Update the feed's active status
+    // Update the feed's active status, reactivating gives a failing feed a fresh start
     sqlx::query(
         r#"
         UPDATE feeds
-        SET active = $1, updated_at = NOW()
+        SET
+            active = $1,
+            consecutive_failures = CASE WHEN $1 THEN 0 ELSE consecutive_failures END,
+            disabled_reason = CASE WHEN $1 THEN NULL ELSE disabled_reason END,
+            updated_at = NOW()
         WHERE id = $2
         "#,
     )
@@ -311,12 +737,20 @@ async fn toggle_feed_status(
 // Refresh all active feeds
 async fn refresh_all_active_feeds(
     State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    // Create a feed fetcher
-    let fetcher = FeedFetcher::new();
-
     // Refresh all active feeds
     let result = fetcher.refresh_all_active_feeds(&pool).await?;
 
     Ok(Json(result))
 }
+
+// Reject refresh intervals the scheduler cannot honour
+fn validate_refresh_interval(minutes: Option<i32>) -> Result<(), AppError> {
+    match minutes {
+        Some(minutes) if minutes < 1 => Err(AppError::ValidationError(
+            "Refresh interval must be at least 1 minute".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
         ArticleSummaryInfo, FeedAggregationRequest, FeedAggregationResponse, FeedSummaryInfo,
         Summary,
     },
+    routes::{articles::has_known_date, AppState},
     summarizer,
 };
 use axum::{
@@ -18,7 +19,7 @@ use sqlx::Row;
 use tracing::{error, info};
 use uuid::Uuid;
 
-pub fn router() -> Router<DbPool> {
+pub fn router() -> Router<AppState> {
     Router::new()
         .route(
             "/articles/:article_id/summary",
@@ -93,20 +94,22 @@ async fn create_summary(
         }
     };
 
-    // Check if article exists and get its content
-    let article = sqlx::query("SELECT id, title, content FROM articles WHERE id = $1")
-        .bind(article_id)
-        .map(|row: sqlx::postgres::PgRow| {
-            (
-                row.get::<Uuid, _>("id"),
-                row.get::<String, _>("title"),
-                row.get::<String, _>("content"),
-            )
-        })
-        .fetch_optional(&pool)
-        .await
-        .map_err(|e| AppError::DatabaseError(e.to_string()))?
-        .ok_or_else(|| AppError::NotFound(format!("Article with ID {} not found", article_id)))?;
+    // Check if article exists and get its plain-text content
+    let article = sqlx::query(
+        "SELECT id, title, COALESCE(content_text, content) AS content FROM articles WHERE id = $1",
+    )
+    .bind(article_id)
+    .map(|row: sqlx::postgres::PgRow| {
+        (
+            row.get::<Uuid, _>("id"),
+            row.get::<String, _>("title"),
+            row.get::<String, _>("content"),
+        )
+    })
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| AppError::DatabaseError(e.to_string()))?
+    .ok_or_else(|| AppError::NotFound(format!("Article with ID {} not found", article_id)))?;
 
     // Check if a summary already exists
     let existing_summary = sqlx::query("SELECT id FROM summaries WHERE article_id = $1")
@@ -227,7 +230,7 @@ async fn create_aggregated_summary(
         }
 
         // Get articles from this feed within the time constraint
-        let articles_data = sqlx::query(
+        let articles_data = sqlx::query(&format!(
             r#"
             SELECT
                 a.id, a.title, a.url, a.published_at,
@@ -236,9 +239,11 @@ async fn create_aggregated_summary(
             LEFT JOIN summaries s ON a.id = s.article_id
             WHERE a.feed_id = $1
                 AND a.published_at >= $2
+                AND {}
             ORDER BY a.published_at DESC
             "#,
-        )
+            has_known_date("a")
+        ))
         .bind(feed_id)
         .bind(cutoff_time)
         .fetch_all(&pool)
@@ -0,0 +1,59 @@
+use crate::{
+    db::DbPool, error::AppError, feeds::FeedFetcher, models::WebSubVerification, routes::AppState,
+};
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use tracing::info;
+use uuid::Uuid;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route(
+        "/websub/:feed_id",
+        get(verify_subscription).post(receive_content),
+    )
+}
+
+// Echo the hub's challenge to confirm a subscription we asked for
+async fn verify_subscription(
+    State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
+    Path(feed_id): Path<Uuid>,
+    Query(verification): Query<WebSubVerification>,
+) -> Result<Response, AppError> {
+    match fetcher.verify_websub(&pool, feed_id, &verification).await? {
+        Some(challenge) => Ok((StatusCode::OK, challenge).into_response()),
+        None => Err(AppError::NotFound(format!(
+            "No pending WebSub {} for feed {}",
+            verification.mode, feed_id
+        ))),
+    }
+}
+
+// Ingest content distributed by the hub
+async fn receive_content(
+    State(pool): State<DbPool>,
+    State(fetcher): State<FeedFetcher>,
+    Path(feed_id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, AppError> {
+    let signature = headers
+        .get("X-Hub-Signature")
+        .and_then(|value| value.to_str().ok());
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+
+    let count = fetcher
+        .receive_websub(&pool, feed_id, signature, content_type, &body)
+        .await?;
+
+    info!("WebSub push added {} articles to feed {}", count, feed_id);
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,328 @@
+use crate::{
+    config::Settings,
+    db::DbPool,
+    feeds::{FeedFetcher, FeedRef},
+};
+use chrono::{DateTime, Datelike, Duration, DurationRound, Timelike, Utc};
+use quick_xml::{events::Event, Reader};
+use rand::Rng;
+use reqwest::header::{HeaderMap, CACHE_CONTROL, EXPIRES};
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// Upper bound for refresh hints published by feeds, so a bogus `<ttl>` cannot
+/// park a feed for months
+const MAX_HINT_MINUTES: i32 = 24 * 60;
+
+/// Refresh hints published by a feed document or its HTTP response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheHints {
+    /// Minimum minutes between refreshes (RSS `<ttl>`, `sy:updatePeriod`, `Cache-Control`)
+    pub ttl_minutes: Option<i32>,
+    /// UTC hours (0-23) during which the feed should not be polled
+    pub skip_hours: Vec<i32>,
+    /// Days of the week (e.g. "Saturday") during which the feed should not be polled
+    pub skip_days: Vec<String>,
+}
+
+impl CacheHints {
+    /// Collect hints from a raw feed document and the `<ttl>` already parsed by `feed_rs`
+    pub fn from_document(xml: &[u8], ttl: Option<u32>) -> Self {
+        let mut hints = CacheHints {
+            ttl_minutes: ttl.map(|ttl| ttl.min(MAX_HINT_MINUTES as u32) as i32),
+            ..Default::default()
+        };
+
+        let mut reader = Reader::from_reader(xml);
+        reader.trim_text(true);
+
+        let mut buf = Vec::new();
+        let mut path: Vec<String> = Vec::new();
+        let mut update_period: Option<String> = None;
+        let mut update_frequency: Option<i32> = None;
+
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(e)) => {
+                    let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                    // Entries come after the channel metadata we care about
+                    if name == "item" || name == "entry" {
+                        break;
+                    }
+                    path.push(name);
+                }
+                Ok(Event::End(_)) => {
+                    path.pop();
+                }
+                Ok(Event::Text(text)) => {
+                    let text = text.unescape().unwrap_or_default();
+                    let text = text.trim();
+                    match path.iter().rev().take(2).collect::<Vec<_>>().as_slice() {
+                        [element, parent] if *element == "hour" && *parent == "skipHours" => {
+                            if let Ok(hour) = text.parse::<i32>() {
+                                // Some publishers use 24 for midnight
+                                hints.skip_hours.push(hour % 24);
+                            }
+                        }
+                        [element, parent] if *element == "day" && *parent == "skipDays" => {
+                            hints.skip_days.push(text.to_string());
+                        }
+                        [element, ..] if *element == "updatePeriod" => {
+                            update_period = Some(text.to_lowercase());
+                        }
+                        [element, ..] if *element == "updateFrequency" => {
+                            update_frequency = text.parse().ok();
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        if let Some(period) = update_period {
+            let period_minutes = match period.as_str() {
+                "hourly" => Some(60),
+                "daily" => Some(24 * 60),
+                "weekly" => Some(7 * 24 * 60),
+                "monthly" => Some(30 * 24 * 60),
+                "yearly" => Some(365 * 24 * 60),
+                _ => None,
+            };
+            if let Some(minutes) = period_minutes {
+                let frequency = update_frequency.unwrap_or(1).max(1);
+                hints.merge_ttl(Some(minutes / frequency));
+            }
+        }
+
+        hints
+    }
+
+    /// Keep the more conservative of the current and the given TTL
+    pub fn merge_ttl(&mut self, ttl_minutes: Option<i32>) {
+        let ttl_minutes = ttl_minutes.map(|ttl| ttl.min(MAX_HINT_MINUTES));
+        self.ttl_minutes = match (self.ttl_minutes, ttl_minutes) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    /// Whether the feed asked not to be polled at the given time
+    fn skips(&self, at: DateTime<Utc>) -> bool {
+        let weekday = at.weekday();
+        self.skip_hours.contains(&(at.hour() as i32))
+            || self
+                .skip_days
+                .iter()
+                .any(|day| day.parse::<chrono::Weekday>() == Ok(weekday))
+    }
+}
+
+/// Freshness lifetime in minutes from `Cache-Control: max-age` or `Expires`
+pub fn http_max_age_minutes(headers: &HeaderMap) -> Option<i32> {
+    let max_age = headers
+        .get(CACHE_CONTROL)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value
+                .split(',')
+                .filter_map(|directive| directive.trim().strip_prefix("max-age="))
+                .find_map(|seconds| seconds.trim_matches('"').parse::<i64>().ok())
+        });
+
+    let seconds = max_age.or_else(|| {
+        headers
+            .get(EXPIRES)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+            .map(|expires| (expires.with_timezone(&Utc) - Utc::now()).num_seconds())
+    })?;
+
+    (seconds > 0).then(|| (seconds / 60).min(MAX_HINT_MINUTES as i64) as i32)
+}
+
+/// Pick a random delay up to `max_jitter_seconds` so feeds do not all fire together
+pub fn random_jitter(max_jitter_seconds: u64) -> Duration {
+    if max_jitter_seconds == 0 {
+        return Duration::zero();
+    }
+    Duration::seconds(rand::thread_rng().gen_range(0..=max_jitter_seconds) as i64)
+}
+
+/// Compute when a feed should next be refreshed.
+///
+/// The feed's interval is stretched to honour any published TTL, then pushed
+/// forward hour by hour until it leaves the feed's skip hours and days.
+pub fn next_fetch_at(
+    now: DateTime<Utc>,
+    interval_minutes: i64,
+    hints: &CacheHints,
+    jitter: Duration,
+) -> DateTime<Utc> {
+    let minutes = hints
+        .ttl_minutes
+        .map_or(interval_minutes, |ttl| interval_minutes.max(ttl as i64));
+    let mut due = now + Duration::minutes(minutes) + jitter;
+
+    // A week of skipped hours means the hints are unsatisfiable, give up after that
+    for _ in 0..(7 * 24) {
+        if !hints.skips(due) {
+            break;
+        }
+        due = due.duration_trunc(Duration::hours(1)).unwrap_or(due) + Duration::hours(1) + jitter;
+    }
+
+    due
+}
+
+/// Minutes to wait before retrying a feed that failed `failures` times in a row.
+///
+/// The regular interval doubles with every failure, up to `max_backoff_minutes`
+/// (or the interval itself if that is longer).
+pub fn backoff_minutes(interval_minutes: i64, failures: i32, max_backoff_minutes: i64) -> i64 {
+    let doublings = failures.saturating_sub(1).clamp(0, 30) as u32;
+    interval_minutes
+        .saturating_mul(1 << doublings)
+        .min(max_backoff_minutes.max(interval_minutes))
+}
+
+/// Spawn the background task that refreshes active feeds when they become due
+pub fn spawn(
+    pool: DbPool,
+    fetcher: FeedFetcher,
+    settings: Settings,
+) -> tokio::task::JoinHandle<()> {
+    info!(
+        "Starting feed refresh scheduler (default interval {} minutes)",
+        settings.scheduler.default_interval_minutes
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(
+            settings.scheduler.poll_interval_seconds,
+        ));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            if let Err(e) = refresh_due_feeds(&pool, &fetcher).await {
+                error!("Scheduled refresh failed: {}", e);
+            }
+            if fetcher.websub_enabled() {
+                if let Err(e) = fetcher.renew_websub_leases(&pool).await {
+                    error!("WebSub lease renewal failed: {}", e);
+                }
+            }
+        }
+    })
+}
+
+/// Refresh every active feed whose next refresh time has passed
+async fn refresh_due_feeds(pool: &DbPool, fetcher: &FeedFetcher) -> Result<(), sqlx::Error> {
+    let due_feeds = sqlx::query(
+        r#"
+        SELECT id, title, url FROM feeds
+        WHERE active = true AND (next_fetch_at IS NULL OR next_fetch_at <= NOW())
+        ORDER BY next_fetch_at NULLS FIRST
+        "#,
+    )
+    .map(|row: sqlx::postgres::PgRow| FeedRef {
+        id: row.get("id"),
+        title: row.get("title"),
+        url: row.get("url"),
+    })
+    .fetch_all(pool)
+    .await?;
+
+    if due_feeds.is_empty() {
+        debug!("No feeds due for refresh");
+        return Ok(());
+    }
+
+    info!("Scheduler refreshing {} due feeds", due_feeds.len());
+
+    for (feed, result) in fetcher.refresh_feeds(pool, due_feeds).await {
+        match result {
+            Ok(saved) => info!(
+                "Scheduled refresh of '{}' added {} articles",
+                feed.title,
+                saved.inserted.len()
+            ),
+            Err(e) => {
+                error!("Scheduled refresh of '{}' failed: {}", feed.title, e);
+                // Errors that did not schedule a retry must not be retried every tick
+                if let Err(e) = fetcher.reschedule(pool, feed.id).await {
+                    error!("Failed to reschedule feed '{}': {}", feed.title, e);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_hints_from_rss_document() {
+        let xml = br#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Test</title><ttl>30</ttl>
+<skipHours><hour>0</hour><hour>24</hour><hour>1</hour></skipHours>
+<skipDays><day>Sunday</day></skipDays>
+<item><title>Entry</title></item>
+</channel></rss>"#;
+
+        let hints = CacheHints::from_document(xml, Some(30));
+        assert_eq!(hints.ttl_minutes, Some(30));
+        assert_eq!(hints.skip_hours, vec![0, 0, 1]);
+        assert_eq!(hints.skip_days, vec!["Sunday".to_string()]);
+    }
+
+    #[test]
+    fn test_hints_from_syndication_module() {
+        let xml = br#"<?xml version="1.0"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:sy="http://purl.org/rss/1.0/modules/syndication/">
+<sy:updatePeriod>daily</sy:updatePeriod><sy:updateFrequency>4</sy:updateFrequency>
+</feed>"#;
+
+        let hints = CacheHints::from_document(xml, None);
+        assert_eq!(hints.ttl_minutes, Some(6 * 60));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_limit() {
+        assert_eq!(backoff_minutes(60, 1, 24 * 60), 60);
+        assert_eq!(backoff_minutes(60, 2, 24 * 60), 120);
+        assert_eq!(backoff_minutes(60, 4, 24 * 60), 480);
+        assert_eq!(backoff_minutes(60, 6, 24 * 60), 24 * 60);
+        assert_eq!(backoff_minutes(60, 1000, 24 * 60), 24 * 60);
+        // A limit below the regular interval never shortens it
+        assert_eq!(backoff_minutes(120, 3, 60), 120);
+    }
+
+    #[test]
+    fn test_next_fetch_at_honours_ttl_and_skip_rules() {
+        // Saturday 2024-01-06 21:00 UTC
+        let now = Utc.with_ymd_and_hms(2024, 1, 6, 21, 0, 0).unwrap();
+        let hints = CacheHints {
+            ttl_minutes: Some(120),
+            skip_hours: vec![0, 1],
+            skip_days: vec!["Sunday".to_string()],
+        };
+
+        // 60 minute interval is stretched to the 2 hour TTL
+        let due = next_fetch_at(now, 60, &CacheHints::default(), Duration::zero());
+        assert_eq!(due, now + Duration::hours(1));
+        let due = next_fetch_at(now, 60, &hints, Duration::zero());
+        assert_eq!(due, now + Duration::hours(2));
+
+        // Landing on Sunday pushes the refresh to Monday 02:00, past the skip hours
+        let due = next_fetch_at(now, 4 * 60, &hints, Duration::zero());
+        assert_eq!(due, Utc.with_ymd_and_hms(2024, 1, 8, 2, 0, 0).unwrap());
+    }
+}